@@ -33,7 +33,7 @@ void list_themes()
     syntect_free_theme_set(theme_set);
 }
 
-void highlight_file(const char *filename, const char *theme_name, const char *theme_path)
+void highlight_file(const char *filename, const char *theme_name, const char *theme_path, const SyntectOptions *options)
 {
     const char *error = NULL;
     SyntectFile *wrapper = NULL;
@@ -51,6 +51,7 @@ void highlight_file(const char *filename, const char *theme_name, const char *th
     }
 
     check_error("create_highlight_file", error);
+    syntect_file_set_options(wrapper, options);
 
     const char *line = NULL;
     while ((line = syntect_highlight_file_line(wrapper, &error)) != NULL)
@@ -68,6 +69,8 @@ void print_help(const char *program_name)
     printf("Options:\n");
     printf("  -t, --theme THEME_NAME   Specify the theme to use for highlighting (default: base16-ocean.dark)\n");
     printf("  -p, --theme-path PATH    Specify the path to a theme file to use for highlighting\n");
+    printf("  -n, --line-numbers       Show line numbers\n");
+    printf("  -H, --highlight-line N   Highlight line N\n");
     printf("  -l, --list-themes        List all available themes\n");
     printf("  -h, --help               Display this help message\n");
 }
@@ -78,16 +81,19 @@ int main(int argc, char *argv[])
     int list_themes_flag = 0;
     const char *theme_name = "base16-ocean.dark";
     const char *theme_path = NULL;
+    SyntectOptions *options = syntect_options_new();
 
     static struct option long_options[] = {
         {"theme", required_argument, 0, 't'},
         {"theme-path", required_argument, 0, 'p'},
+        {"line-numbers", no_argument, 0, 'n'},
+        {"highlight-line", required_argument, 0, 'H'},
         {"list-themes", no_argument, 0, 'l'},
         {"help", no_argument, 0, 'h'},
         {0, 0, 0, 0}};
 
     // Parse command line options
-    while ((opt = getopt_long(argc, argv, "t:p:lh", long_options, NULL)) != -1)
+    while ((opt = getopt_long(argc, argv, "t:p:nH:lh", long_options, NULL)) != -1)
     {
         switch (opt)
         {
@@ -97,6 +103,12 @@ int main(int argc, char *argv[])
         case 'p':
             theme_path = optarg;
             break;
+        case 'n':
+            syntect_options_set_line_numbers(options, true);
+            break;
+        case 'H':
+            syntect_options_set_highlighted_line(options, strtoul(optarg, NULL, 10));
+            break;
         case 'l':
             list_themes_flag = 1;
             break;
@@ -124,8 +136,10 @@ int main(int argc, char *argv[])
 
     for (int i = optind; i < argc; i++)
     {
-        highlight_file(argv[i], theme_name, theme_path);
+        highlight_file(argv[i], theme_name, theme_path, options);
     }
 
+    syntect_options_free(options);
+
     return 0;
 }
@@ -0,0 +1,254 @@
+//! Theme generator for Base16 and Base24 color schemes.
+
+use crate::theme_builder::{format_color, parse_theme_item, set_theme_setting};
+use crate::{load_theme_with, set_error, str_arg, SyntectTheme};
+use libc::c_char;
+use std::path::Path;
+use std::ptr;
+use syntect::highlighting::{Color, Theme};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// A template rule: scope selector, foreground slot, background slot and font style.
+type TemplateRule = (
+    &'static str,
+    Option<usize>,
+    Option<usize>,
+    Option<&'static str>,
+);
+
+/// The scope to palette slot template, following the Base16 TextMate template.
+const TEMPLATE: [TemplateRule; 41] = [
+    ("variable.parameter.function", Some(0x05), None, None),
+    ("comment, punctuation.definition.comment", Some(0x03), None, None),
+    ("punctuation.definition.string, punctuation.definition.variable, punctuation.definition.parameters, punctuation.definition.array", Some(0x05), None, None),
+    ("keyword.operator", Some(0x05), None, None),
+    ("keyword", Some(0x0E), None, None),
+    ("variable", Some(0x08), None, None),
+    ("entity.name.function, meta.require, support.function.any-method, variable.function, variable.annotation, support.macro", Some(0x0D), None, None),
+    ("entity.name.label", Some(0x0F), None, None),
+    ("support.class, entity.name.class, entity.name.type.class", Some(0x0A), None, None),
+    ("meta.class", Some(0x07), None, None),
+    ("keyword.other.special-method", Some(0x0D), None, None),
+    ("storage", Some(0x0E), None, None),
+    ("support.function", Some(0x0C), None, None),
+    ("string, constant.other.symbol, entity.other.inherited-class", Some(0x0B), None, None),
+    ("constant.numeric", Some(0x09), None, None),
+    ("constant", Some(0x09), None, None),
+    ("entity.name.tag", Some(0x08), None, None),
+    ("entity.other.attribute-name", Some(0x09), None, None),
+    ("entity.other.attribute-name.id, punctuation.definition.entity", Some(0x0D), None, None),
+    ("meta.selector", Some(0x0E), None, None),
+    ("markup.heading punctuation.definition.heading, entity.name.section", Some(0x0D), None, Some("")),
+    ("keyword.other.unit", Some(0x09), None, None),
+    ("markup.bold, punctuation.definition.bold", Some(0x0A), None, Some("bold")),
+    ("markup.italic, punctuation.definition.italic", Some(0x0E), None, Some("italic")),
+    ("markup.raw.inline", Some(0x0B), None, None),
+    ("string.other.link, punctuation.definition.string.end.markdown, punctuation.definition.string.begin.markdown", Some(0x08), None, None),
+    ("meta.link", Some(0x09), None, None),
+    ("markup.list", Some(0x08), None, None),
+    ("markup.quote", Some(0x09), None, None),
+    ("meta.separator", Some(0x05), Some(0x02), None),
+    ("markup.inserted", Some(0x0B), None, None),
+    ("markup.deleted", Some(0x08), None, None),
+    ("markup.changed", Some(0x0E), None, None),
+    ("constant.other.color", Some(0x0C), None, None),
+    ("string.regexp", Some(0x0C), None, None),
+    ("constant.character.escape", Some(0x0C), None, None),
+    ("punctuation.section.embedded, variable.interpolation", Some(0x0E), None, None),
+    ("invalid.illegal", Some(0x07), Some(0x08), None),
+    ("invalid.broken", Some(0x00), Some(0x09), None),
+    ("invalid.deprecated", Some(0x07), Some(0x0F), None),
+    ("invalid.unimplemented", Some(0x07), Some(0x03), None),
+];
+
+/// Loads a Base16 or Base24 scheme YAML file and builds a theme from it.
+pub(crate) fn load_base16_theme(path: &Path) -> Result<Theme, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error loading scheme: {}", e))?;
+    base16_theme_from_yaml(&text)
+}
+
+/// Builds a theme from the text of a Base16 or Base24 scheme.
+///
+/// Both the classic layout (`scheme:` with top-level `base00`..`base0F` hex values) and the
+/// newer layout (`system:`, `name:` and a `palette:` map) are accepted. A scheme that defines
+/// `base10`..`base17` is treated as Base24.
+pub(crate) fn base16_theme_from_yaml(text: &str) -> Result<Theme, String> {
+    let docs =
+        YamlLoader::load_from_str(text).map_err(|e| format!("Invalid scheme YAML: {}", e))?;
+    let doc = docs
+        .first()
+        .ok_or_else(|| "Scheme YAML is empty".to_string())?;
+
+    let palette_map = match &doc["palette"] {
+        Yaml::BadValue => doc,
+        palette => palette,
+    };
+    let slot = |index: usize| -> Result<Option<Color>, String> {
+        let key = format!("base{:02X}", index);
+        match palette_map[key.as_str()].as_str() {
+            Some(value) => parse_hex(value)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid color for {}: '{}'", key, value)),
+            None => Ok(None),
+        }
+    };
+
+    let mut palette = Vec::with_capacity(24);
+    for index in 0..16 {
+        palette.push(slot(index)?.ok_or_else(|| format!("Missing color base{:02X}", index))?);
+    }
+    let extras = (0x10..0x18).map(slot).collect::<Result<Vec<_>, _>>()?;
+    let is_base24 = extras.iter().all(Option::is_some) || doc["system"].as_str() == Some("base24");
+    if is_base24 {
+        for (index, color) in extras.into_iter().enumerate() {
+            palette.push(color.ok_or_else(|| format!("Missing color base{:02X}", index + 0x10))?);
+        }
+    }
+
+    let system = if is_base24 { "Base24" } else { "Base16" };
+    let scheme_name = doc["name"].as_str().or_else(|| doc["scheme"].as_str());
+    let mut theme = Theme {
+        name: Some(match scheme_name {
+            Some(name) => format!("{} {}", system, name),
+            None => system.to_string(),
+        }),
+        author: doc["author"].as_str().map(|author| author.to_string()),
+        ..Theme::default()
+    };
+
+    let hex = |index: usize| format_color(palette[index]);
+    let mut line_highlight = palette[0x03];
+    line_highlight.a = 0x55;
+    let gutter = if is_base24 { 0x10 } else { 0x01 };
+    let globals = [
+        ("background", hex(0x00)),
+        ("foreground", hex(0x05)),
+        ("caret", hex(0x05)),
+        ("selection", hex(0x02)),
+        ("lineHighlight", format_color(line_highlight)),
+        ("gutter", hex(gutter)),
+        ("gutterForeground", hex(0x03)),
+    ];
+    for (key, value) in globals {
+        set_theme_setting(&mut theme.settings, key, Some(&value))?;
+    }
+
+    for (scope, foreground, background, font_style) in TEMPLATE {
+        let item = parse_theme_item(
+            scope,
+            foreground.map(hex).as_deref(),
+            background.map(hex).as_deref(),
+            font_style,
+        )?;
+        theme.scopes.push(item);
+    }
+
+    Ok(theme)
+}
+
+/// Parses a palette color, with or without the leading `#`.
+fn parse_hex(value: &str) -> Option<Color> {
+    let value = value.trim().trim_start_matches('#');
+    if value.len() != 6 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&value[i..i + 2], 16).ok();
+    Some(Color {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+        a: 0xff,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_load_base16_theme(
+    scheme_path: *const c_char,
+    enable_caching: bool,
+    error: *mut *const c_char,
+) -> *mut SyntectTheme {
+    let Some(scheme_path) = str_arg(scheme_path, "Invalid scheme path", error) else {
+        return ptr::null_mut();
+    };
+
+    match load_theme_with(Path::new(scheme_path), enable_caching, load_base16_theme) {
+        Ok(theme) => Box::into_raw(Box::new(SyntectTheme { theme })),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_theme, syntect_free_theme};
+    use std::ffi::{CStr, CString};
+    use syntect::easy::HighlightLines;
+    use syntect::parsing::SyntaxSet;
+
+    #[test]
+    fn test_matches_base16_textmate_theme() {
+        let scheme_path = CString::new("test/themes/base16-ocean.yaml").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let theme = syntect_load_base16_theme(scheme_path.as_ptr(), false, &mut error);
+        assert!(!theme.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+        let generated = unsafe { &(*theme).theme };
+        let reference = load_theme("test/themes/base16-ocean.tmTheme", false).unwrap();
+
+        assert_eq!(generated.name, reference.name);
+        assert_eq!(generated.settings.background, reference.settings.background);
+        assert_eq!(
+            generated.settings.line_highlight,
+            reference.settings.line_highlight
+        );
+
+        let ss = SyntaxSet::load_defaults_newlines();
+        let syntax = ss.find_syntax_by_extension("rs").unwrap();
+        let code = "// comment\nfn main() { let x = 42; println!(\"{}\", x); }\n";
+        let mut generated_lines = HighlightLines::new(syntax, generated);
+        let mut reference_lines = HighlightLines::new(syntax, &reference);
+        for line in code.lines() {
+            assert_eq!(
+                generated_lines.highlight_line(line, &ss).unwrap(),
+                reference_lines.highlight_line(line, &ss).unwrap()
+            );
+        }
+
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_base24_palette_format() {
+        let mut yaml =
+            String::from("system: \"base24\"\nname: \"Test\"\nvariant: \"dark\"\npalette:\n");
+        for index in 0..0x18 {
+            yaml.push_str(&format!(
+                "  base{:02X}: \"#{:02x}{:02x}{:02x}\"\n",
+                index, index, index, index
+            ));
+        }
+
+        let theme = base16_theme_from_yaml(&yaml).unwrap();
+        assert_eq!(theme.name.as_deref(), Some("Base24 Test"));
+        assert_eq!(
+            theme.settings.gutter,
+            Some(Color {
+                r: 0x10,
+                g: 0x10,
+                b: 0x10,
+                a: 0xff
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_color() {
+        let err = base16_theme_from_yaml("scheme: \"Broken\"\nbase00: \"000000\"\n").unwrap_err();
+        assert!(err.contains("base01"), "{}", err);
+    }
+}
@@ -0,0 +1,358 @@
+//! Variants of the highlighting functions that take and return length-delimited buffers, so
+//! callers can pass slices of larger buffers and text containing NUL characters.
+
+use crate::options::SyntectOptions;
+use crate::{get_syntax_and_theme, initialize, set_error, str_arg, SyntectFile, SyntectLines};
+use libc::c_char;
+use std::ptr;
+use syntect::highlighting::Theme;
+use syntect::util::LinesWithEndings;
+
+/// Converts a `(data, len)` argument, reporting `message` through `error` if it is not valid
+/// UTF-8. `data` may be NULL when `len` is 0.
+pub(crate) fn bytes_arg<'a>(
+    data: *const c_char,
+    len: usize,
+    message: &str,
+    error: *mut *const c_char,
+) -> Option<&'a str> {
+    if data.is_null() {
+        if len == 0 {
+            return Some("");
+        }
+        set_error(error, message);
+        return None;
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, len) };
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_error(error, message);
+            None
+        }
+    }
+}
+
+/// Hands output to C as a buffer of `*out_len` bytes, followed by a NUL that is not counted.
+/// The buffer is freed with `syntect_free_buffer`.
+pub(crate) fn output_buffer(output: String, out_len: *mut usize) -> *const c_char {
+    assert!(!out_len.is_null());
+    let mut bytes = output.into_bytes();
+    unsafe {
+        *out_len = bytes.len();
+    }
+    bytes.push(0);
+    Box::into_raw(bytes.into_boxed_slice()) as *const c_char
+}
+
+/// Carries out a call that highlights a whole document given as a `(data, len)` buffer with
+/// `highlight`, reporting `message` if the text is not valid UTF-8. NULL options stand for
+/// the defaults.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn highlight_document_buffer(
+    data: *const c_char,
+    len: usize,
+    theme_name: *const c_char,
+    options: *const SyntectOptions,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+    message: &str,
+    highlight: fn(&str, &'static Theme, &SyntectOptions) -> Result<String, String>,
+) -> *const c_char {
+    initialize();
+
+    let Some(text) = bytes_arg(data, len, message, error) else {
+        return ptr::null();
+    };
+    let Some(theme_name) = str_arg(theme_name, "Invalid theme name", error) else {
+        return ptr::null();
+    };
+    let default_options = SyntectOptions::default();
+    let options = if options.is_null() {
+        &default_options
+    } else {
+        unsafe { &*options }
+    };
+
+    // `_registered` keeps a registered theme alive while it is used.
+    let (_, theme, _registered) = match get_syntax_and_theme(theme_name) {
+        Ok(result) => result,
+        Err(err) => {
+            set_error(error, err);
+            return ptr::null();
+        }
+    };
+
+    match highlight(text, theme, options) {
+        Ok(output) => output_buffer(output, out_len),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_highlight_file_line_len(
+    wrapper: *mut SyntectFile,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+
+    match wrapper.highlight_next_line() {
+        Ok(Some(highlighted_line)) => output_buffer(highlighted_line, out_len),
+        Ok(None) => ptr::null(),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_highlight_text_line_len(
+    wrapper: *mut SyntectLines,
+    data: *const c_char,
+    len: usize,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Some(line) = bytes_arg(data, len, "Invalid input line", error) else {
+        return ptr::null();
+    };
+
+    match wrapper.highlight_line(line) {
+        Ok(highlighted_line) => output_buffer(highlighted_line, out_len),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_highlight_buffer(
+    wrapper: *mut SyntectLines,
+    data: *const c_char,
+    len: usize,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Some(text) = bytes_arg(data, len, "Invalid input text", error) else {
+        return ptr::null();
+    };
+
+    wrapper.output.set_whole_document(true);
+    let mut output = wrapper.output.begin_document();
+    let result = LinesWithEndings::from(text).try_for_each(|line| {
+        output.push_str(&wrapper.highlight_line(line)?);
+        Ok::<_, String>(())
+    });
+    if result.is_ok() {
+        wrapper.output.close_document(&mut output);
+    }
+    wrapper.output.set_whole_document(false);
+
+    match result {
+        Ok(()) => output_buffer(output, out_len),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_free_buffer(data: *mut c_char, len: usize) {
+    if !data.is_null() {
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                data as *mut u8,
+                len + 1,
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::syntect_lines_set_options;
+    use crate::options::{
+        syntect_options_free, syntect_options_new, syntect_options_set_format,
+        syntect_options_set_line_endings,
+    };
+    use crate::{syntect_create_lines, syntect_free_lines};
+    use std::ffi::CString;
+
+    fn create_lines() -> *mut SyntectLines {
+        let theme_name = CString::new("base16-ocean.dark").unwrap();
+        let mut error: *const c_char = ptr::null();
+        let lines = syntect_create_lines(theme_name.as_ptr(), &mut error);
+        assert!(!lines.is_null());
+        lines
+    }
+
+    #[test]
+    fn test_highlight_text_line_len() {
+        let lines = create_lines();
+        let mut error: *const c_char = ptr::null();
+        // Only the first line of the buffer is passed in, and it contains a NUL.
+        let data = b"let a = \"\0\";\nignored\n";
+        let mut out_len = 0;
+
+        let output = syntect_highlight_text_line_len(
+            lines,
+            data.as_ptr() as *const c_char,
+            13,
+            &mut out_len,
+            &mut error,
+        );
+        assert!(!output.is_null());
+        let bytes = unsafe { std::slice::from_raw_parts(output as *const u8, out_len) };
+        let text = std::str::from_utf8(bytes).unwrap();
+        assert!(text.contains('\0'));
+        assert!(text.ends_with('\n'));
+        assert!(!text.contains("ignored"));
+        syntect_free_buffer(output as *mut c_char, out_len);
+
+        let invalid = b"\xff\n";
+        let output = syntect_highlight_text_line_len(
+            lines,
+            invalid.as_ptr() as *const c_char,
+            invalid.len(),
+            &mut out_len,
+            &mut error,
+        );
+        assert!(output.is_null());
+        assert!(!error.is_null());
+        crate::syntect_free_string(error as *mut c_char);
+
+        syntect_free_lines(lines);
+    }
+
+    #[test]
+    fn test_highlight_buffer() {
+        let lines = create_lines();
+        let options = syntect_options_new();
+        let mut error: *const c_char = ptr::null();
+        assert!(syntect_options_set_format(options, 1, &mut error));
+        syntect_lines_set_options(lines, options);
+        syntect_options_free(options);
+
+        let data = "fn a() {}\nfn b() {}\n";
+        let mut out_len = 0;
+        let output = syntect_highlight_buffer(
+            lines,
+            data.as_ptr() as *const c_char,
+            data.len(),
+            &mut out_len,
+            &mut error,
+        );
+        assert!(!output.is_null());
+        let bytes = unsafe { std::slice::from_raw_parts(output as *const u8, out_len) };
+        let html = std::str::from_utf8(bytes).unwrap();
+        assert!(html.starts_with("<pre "));
+        assert!(html.ends_with("</pre>\n"));
+        assert_eq!(html.matches('\n').count(), 4);
+        syntect_free_buffer(output as *mut c_char, out_len);
+
+        syntect_free_lines(lines);
+    }
+
+    #[test]
+    fn test_svg_needs_whole_text() {
+        let lines = create_lines();
+        let options = syntect_options_new();
+        let mut error: *const c_char = ptr::null();
+        assert!(syntect_options_set_format(options, 5, &mut error));
+        syntect_lines_set_options(lines, options);
+        syntect_options_free(options);
+
+        // Line by line, the size of the image could never be filled in.
+        assert!(crate::syntect_lines_begin_document(lines, &mut error).is_null());
+        assert!(!error.is_null());
+        crate::syntect_free_string(error as *mut c_char);
+        error = ptr::null();
+        let line = CString::new("fn a() {}\n").unwrap();
+        assert!(crate::syntect_highlight_text_line(lines, line.as_ptr(), &mut error).is_null());
+        assert!(!error.is_null());
+        crate::syntect_free_string(error as *mut c_char);
+        error = ptr::null();
+        assert!(crate::syntect_lines_end_document(lines, &mut error).is_null());
+        crate::syntect_free_string(error as *mut c_char);
+        error = ptr::null();
+
+        let data = "fn a() {}\n";
+        let mut out_len = 0;
+        let output = syntect_highlight_buffer(
+            lines,
+            data.as_ptr() as *const c_char,
+            data.len(),
+            &mut out_len,
+            &mut error,
+        );
+        assert!(!output.is_null());
+        let bytes = unsafe { std::slice::from_raw_parts(output as *const u8, out_len) };
+        let svg = std::str::from_utf8(bytes).unwrap();
+        assert!(svg.starts_with("<svg width=\""), "{}", svg);
+        syntect_free_buffer(output as *mut c_char, out_len);
+
+        syntect_free_lines(lines);
+    }
+
+    #[test]
+    fn test_highlight_buffer_strip() {
+        let lines = create_lines();
+        let options = syntect_options_new();
+        let mut error: *const c_char = ptr::null();
+        assert!(syntect_options_set_format(options, 1, &mut error));
+        assert!(syntect_options_set_line_endings(options, 2, &mut error));
+        syntect_lines_set_options(lines, options);
+        syntect_options_free(options);
+
+        // Stripping line endings still leaves one row per line of the text.
+        let data = "fn a() {}\r\nfn b() {}\n";
+        let mut out_len = 0;
+        let output = syntect_highlight_buffer(
+            lines,
+            data.as_ptr() as *const c_char,
+            data.len(),
+            &mut out_len,
+            &mut error,
+        );
+        assert!(!output.is_null());
+        let bytes = unsafe { std::slice::from_raw_parts(output as *const u8, out_len) };
+        let html = std::str::from_utf8(bytes).unwrap();
+        let rows: Vec<&str> = html.split('\n').collect();
+        assert_eq!(rows.len(), 5, "{}", html);
+        assert!(rows[1].contains(">a</span>") && !rows[1].contains(">b</span>"));
+        assert!(rows[2].contains(">b</span>"));
+        assert!(!html.contains('\r'));
+        syntect_free_buffer(output as *mut c_char, out_len);
+
+        // Lines highlighted one by one still come without endings.
+        let line = CString::new("fn c() {}\n").unwrap();
+        let output = crate::syntect_highlight_text_line(lines, line.as_ptr(), &mut error);
+        assert!(!output.is_null());
+        let text = unsafe { std::ffi::CStr::from_ptr(output) }
+            .to_str()
+            .unwrap();
+        assert!(!text.contains('\n'));
+        crate::syntect_free_string(output as *mut c_char);
+
+        syntect_free_lines(lines);
+    }
+}
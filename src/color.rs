@@ -0,0 +1,693 @@
+//! CSS color parsing and color arithmetic shared by the theme loaders.
+
+use std::collections::HashMap;
+use syntect::highlighting::Color;
+
+/// Draws a possibly translucent color over an opaque one, rounding as syntect does when it
+/// blends a foreground into the background.
+pub(crate) fn blend(color: Color, under: Color) -> Color {
+    if color.a == 0xff {
+        return color;
+    }
+    let ratio = color.a as u32;
+    let mix =
+        |top: u8, bottom: u8| ((top as u32 * ratio + bottom as u32 * (255 - ratio)) / 255) as u8;
+    Color {
+        r: mix(color.r, under.r),
+        g: mix(color.g, under.g),
+        b: mix(color.b, under.b),
+        a: 0xff,
+    }
+}
+
+/// Parses a CSS color: hex notation, `rgb()`/`rgba()`, `hsl()`/`hsla()`, `hwb()`, a named
+/// color, `var(name)` or a Sublime Text `color()` mod function.
+///
+/// `variables` maps the names usable in `var()` to their unresolved values.
+pub(crate) fn parse_css_color(
+    s: &str,
+    variables: &HashMap<String, String>,
+) -> Result<Color, String> {
+    let mut parser = Parser {
+        input: s,
+        pos: 0,
+        variables,
+        depth: 0,
+    };
+    let color = parser.color()?;
+    parser.skip_whitespace();
+    if parser.pos != s.len() {
+        return Err(format!("Invalid color '{}'", s));
+    }
+    Ok(color)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    variables: &'a HashMap<String, String>,
+    depth: usize,
+}
+
+/// A number argument, with whether it was a percentage and whether it was a relative
+/// (`+ 10%`, `- 0.2`) adjustment.
+#[derive(Clone, Copy)]
+struct Number {
+    value: f64,
+    percent: bool,
+    relative: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> String {
+        format!("Invalid color '{}'", self.input)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn ident(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn number(&mut self) -> Result<Number, String> {
+        self.skip_whitespace();
+        let mut relative = false;
+        let mut sign = 1.0;
+        if self.rest().starts_with(['+', '-']) {
+            let after = &self.rest()[1..];
+            // "+ 10%" is a relative adjustment, "-10" is just a negative number.
+            if after.starts_with(char::is_whitespace) || self.rest().starts_with('+') {
+                relative = true;
+            }
+            if self.rest().starts_with('-') {
+                sign = -1.0;
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..len].parse().map_err(|_| self.error())?;
+        self.pos += len;
+        let percent = self.rest().starts_with('%');
+        if percent {
+            self.pos += 1;
+        } else if self.rest().starts_with("deg") {
+            self.pos += 3;
+        }
+        Ok(Number {
+            value: sign * value,
+            percent,
+            relative,
+        })
+    }
+
+    /// Reads the numeric arguments of `rgb()`-style functions, separated by commas,
+    /// whitespace or a `/` before the alpha value.
+    fn number_list(&mut self) -> Result<Vec<Number>, String> {
+        let mut numbers = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.eat(')') {
+                return Ok(numbers);
+            }
+            if !numbers.is_empty() && !self.eat(',') {
+                self.eat('/');
+            }
+            numbers.push(self.number()?);
+        }
+    }
+
+    fn color(&mut self) -> Result<Color, String> {
+        self.depth += 1;
+        if self.depth > 32 {
+            return Err(format!("Color '{}' is nested too deeply", self.input));
+        }
+        let color = self.color_inner();
+        self.depth -= 1;
+        color
+    }
+
+    fn color_inner(&mut self) -> Result<Color, String> {
+        self.skip_whitespace();
+        if self.rest().starts_with('#') {
+            self.pos += 1;
+            let rest = self.rest();
+            let len = rest
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(rest.len());
+            self.pos += len;
+            return parse_hex(&rest[..len]).ok_or_else(|| self.error());
+        }
+
+        let name = self.ident().to_ascii_lowercase();
+        if name.is_empty() {
+            return Err(self.error());
+        }
+        if !self.eat('(') {
+            return named_color(&name).ok_or_else(|| format!("Unknown color name '{}'", name));
+        }
+
+        match name.as_str() {
+            "var" => {
+                let var = self.ident();
+                self.expect(')')?;
+                let value = self
+                    .variables
+                    .get(var)
+                    .ok_or_else(|| format!("Undefined variable '{}'", var))?;
+                let mut parser = Parser {
+                    input: value,
+                    pos: 0,
+                    variables: self.variables,
+                    depth: self.depth,
+                };
+                let color = parser.color()?;
+                parser.skip_whitespace();
+                if parser.pos != value.len() {
+                    return Err(parser.error());
+                }
+                Ok(color)
+            }
+            "rgb" | "rgba" => {
+                let args = self.number_list()?;
+                if args.len() < 3 || args.len() > 4 {
+                    return Err(self.error());
+                }
+                let channel = |n: Number| {
+                    if n.percent {
+                        n.value * 2.55
+                    } else {
+                        n.value
+                    }
+                };
+                Ok(from_rgba(
+                    channel(args[0]),
+                    channel(args[1]),
+                    channel(args[2]),
+                    args.get(3).map_or(1.0, |&a| alpha_value(a)),
+                ))
+            }
+            "hsl" | "hsla" => {
+                let args = self.number_list()?;
+                if args.len() < 3 || args.len() > 4 {
+                    return Err(self.error());
+                }
+                let (r, g, b) =
+                    hsl_to_rgb(args[0].value, args[1].value / 100.0, args[2].value / 100.0);
+                Ok(from_rgba(
+                    r,
+                    g,
+                    b,
+                    args.get(3).map_or(1.0, |&a| alpha_value(a)),
+                ))
+            }
+            "hwb" => {
+                let args = self.number_list()?;
+                if args.len() < 3 || args.len() > 4 {
+                    return Err(self.error());
+                }
+                let (r, g, b) =
+                    hwb_to_rgb(args[0].value, args[1].value / 100.0, args[2].value / 100.0);
+                Ok(from_rgba(
+                    r,
+                    g,
+                    b,
+                    args.get(3).map_or(1.0, |&a| alpha_value(a)),
+                ))
+            }
+            "color" => {
+                let mut color = self.color()?;
+                while !self.eat(')') {
+                    color = self.adjust(color)?;
+                }
+                Ok(color)
+            }
+            _ => Err(format!("Unknown color function '{}'", name)),
+        }
+    }
+
+    /// Applies one `color()` adjuster such as `alpha(0.5)` or `blend(#fff 20%)`.
+    fn adjust(&mut self, color: Color) -> Result<Color, String> {
+        let name = self.ident().to_ascii_lowercase();
+        self.expect('(')?;
+        let color = match name.as_str() {
+            "alpha" | "a" => {
+                let n = self.number()?;
+                let alpha = adjust_value(color.a as f64 / 255.0, n, 1.0);
+                with_alpha(color, alpha)
+            }
+            "lightness" | "l" | "saturation" | "s" => {
+                let n = self.number()?;
+                let (h, s, l) = rgb_to_hsl(color);
+                let (s, l) = if name.starts_with('l') {
+                    (s, adjust_value(l, n, 100.0))
+                } else {
+                    (adjust_value(s, n, 100.0), l)
+                };
+                let (r, g, b) = hsl_to_rgb(h, s, l);
+                from_rgba(r, g, b, color.a as f64 / 255.0)
+            }
+            "blend" | "blenda" => {
+                let other = self.color()?;
+                let n = self.number()?;
+                let space = self.ident().to_ascii_lowercase();
+                let keep = if n.percent { n.value / 100.0 } else { n.value };
+                blend_mod(
+                    color,
+                    other,
+                    keep.clamp(0.0, 1.0),
+                    name == "blenda",
+                    space == "hsl",
+                )
+            }
+            "min-contrast" => {
+                let other = self.color()?;
+                let ratio = self.number()?.value;
+                min_contrast(color, other, ratio)
+            }
+            _ => return Err(format!("Unknown color adjuster '{}'", name)),
+        };
+        self.expect(')')?;
+        Ok(color)
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 | 4 => Some(Color {
+            r: digit(0)? * 17,
+            g: digit(1)? * 17,
+            b: digit(2)? * 17,
+            a: if hex.len() == 4 { digit(3)? * 17 } else { 0xff },
+        }),
+        6 | 8 => Some(Color {
+            r: pair(0)?,
+            g: pair(2)?,
+            b: pair(4)?,
+            a: if hex.len() == 8 { pair(6)? } else { 0xff },
+        }),
+        _ => None,
+    }
+}
+
+fn alpha_value(n: Number) -> f64 {
+    if n.percent {
+        n.value / 100.0
+    } else {
+        n.value
+    }
+}
+
+/// Applies an absolute or relative adjustment to a value in `0..=scale`, where `value`
+/// itself is normalized to `0..=1`.
+fn adjust_value(value: f64, n: Number, scale: f64) -> f64 {
+    let amount = if n.percent {
+        n.value / 100.0
+    } else {
+        n.value / scale
+    };
+    let adjusted = if n.relative { value + amount } else { amount };
+    adjusted.clamp(0.0, 1.0)
+}
+
+fn from_rgba(r: f64, g: f64, b: f64, a: f64) -> Color {
+    let channel = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+    Color {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b),
+        a: channel(a * 255.0),
+    }
+}
+
+fn with_alpha(color: Color, alpha: f64) -> Color {
+    Color {
+        a: (alpha * 255.0).round().clamp(0.0, 255.0) as u8,
+        ..color
+    }
+}
+
+/// Mixes `keep` of `base` with the rest taken from `other`.
+fn blend_mod(base: Color, other: Color, keep: f64, include_alpha: bool, hsl: bool) -> Color {
+    let mix = |a: f64, b: f64| a * keep + b * (1.0 - keep);
+    let alpha = if include_alpha {
+        mix(base.a as f64, other.a as f64) / 255.0
+    } else {
+        base.a as f64 / 255.0
+    };
+    if hsl {
+        let (h1, s1, l1) = rgb_to_hsl(base);
+        let (h2, s2, l2) = rgb_to_hsl(other);
+        let (r, g, b) = hsl_to_rgb(mix(h1, h2), mix(s1, s2), mix(l1, l2));
+        from_rgba(r, g, b, alpha)
+    } else {
+        from_rgba(
+            mix(base.r as f64, other.r as f64),
+            mix(base.g as f64, other.g as f64),
+            mix(base.b as f64, other.b as f64),
+            alpha,
+        )
+    }
+}
+
+/// Moves the lightness of `color` away from `other` until their contrast ratio reaches `ratio`.
+fn min_contrast(color: Color, other: Color, ratio: f64) -> Color {
+    if contrast_ratio(color, other) >= ratio {
+        return color;
+    }
+    let (h, s, l) = rgb_to_hsl(color);
+    let lighter = relative_luminance(other) < 0.5;
+    let mut best = color;
+    for step in 1..=100 {
+        let l = if lighter {
+            l + (1.0 - l) * step as f64 / 100.0
+        } else {
+            l * (1.0 - step as f64 / 100.0)
+        };
+        let (r, g, b) = hsl_to_rgb(h, s, l);
+        best = from_rgba(r, g, b, color.a as f64 / 255.0);
+        if contrast_ratio(best, other) >= ratio {
+            break;
+        }
+    }
+    best
+}
+
+/// Converts a color to hue (degrees), saturation and lightness (both `0..=1`).
+pub(crate) fn rgb_to_hsl(color: Color) -> (f64, f64, f64) {
+    let r = color.r as f64 / 255.0;
+    let g = color.g as f64 / 255.0;
+    let b = color.b as f64 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d == 0.0 {
+        return (0.0, 0.0, l);
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * (((g - b) / d) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / d + 2.0)
+    } else {
+        60.0 * ((r - g) / d + 4.0)
+    };
+    (h.rem_euclid(360.0), s, l)
+}
+
+/// Converts hue (degrees), saturation and lightness (both `0..=1`) to RGB channels in `0..=255`.
+pub(crate) fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (f64, f64, f64) {
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    ((r + m) * 255.0, (g + m) * 255.0, (b + m) * 255.0)
+}
+
+fn hwb_to_rgb(h: f64, w: f64, b: f64) -> (f64, f64, f64) {
+    let (w, b) = if w + b > 1.0 {
+        (w / (w + b), b / (w + b))
+    } else {
+        (w, b)
+    };
+    let (r, g, bl) = hsl_to_rgb(h, 1.0, 0.5);
+    let f = |c: f64| (c / 255.0 * (1.0 - w - b) + w) * 255.0;
+    (f(r), f(g), f(bl))
+}
+
+/// The WCAG relative luminance of a color, ignoring its alpha.
+pub(crate) fn relative_luminance(color: Color) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// The WCAG contrast ratio between two colors, from 1 to 21.
+pub(crate) fn contrast_ratio(a: Color, b: Color) -> f64 {
+    let la = relative_luminance(a);
+    let lb = relative_luminance(b);
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    let hex = match name {
+        "transparent" => {
+            return Some(Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            })
+        }
+        "aliceblue" => 0xf0f8ff,
+        "antiquewhite" => 0xfaebd7,
+        "aqua" | "cyan" => 0x00ffff,
+        "aquamarine" => 0x7fffd4,
+        "azure" => 0xf0ffff,
+        "beige" => 0xf5f5dc,
+        "bisque" => 0xffe4c4,
+        "black" => 0x000000,
+        "blanchedalmond" => 0xffebcd,
+        "blue" => 0x0000ff,
+        "blueviolet" => 0x8a2be2,
+        "brown" => 0xa52a2a,
+        "burlywood" => 0xdeb887,
+        "cadetblue" => 0x5f9ea0,
+        "chartreuse" => 0x7fff00,
+        "chocolate" => 0xd2691e,
+        "coral" => 0xff7f50,
+        "cornflowerblue" => 0x6495ed,
+        "cornsilk" => 0xfff8dc,
+        "crimson" => 0xdc143c,
+        "darkblue" => 0x00008b,
+        "darkcyan" => 0x008b8b,
+        "darkgoldenrod" => 0xb8860b,
+        "darkgray" | "darkgrey" => 0xa9a9a9,
+        "darkgreen" => 0x006400,
+        "darkkhaki" => 0xbdb76b,
+        "darkmagenta" => 0x8b008b,
+        "darkolivegreen" => 0x556b2f,
+        "darkorange" => 0xff8c00,
+        "darkorchid" => 0x9932cc,
+        "darkred" => 0x8b0000,
+        "darksalmon" => 0xe9967a,
+        "darkseagreen" => 0x8fbc8f,
+        "darkslateblue" => 0x483d8b,
+        "darkslategray" | "darkslategrey" => 0x2f4f4f,
+        "darkturquoise" => 0x00ced1,
+        "darkviolet" => 0x9400d3,
+        "deeppink" => 0xff1493,
+        "deepskyblue" => 0x00bfff,
+        "dimgray" | "dimgrey" => 0x696969,
+        "dodgerblue" => 0x1e90ff,
+        "firebrick" => 0xb22222,
+        "floralwhite" => 0xfffaf0,
+        "forestgreen" => 0x228b22,
+        "fuchsia" | "magenta" => 0xff00ff,
+        "gainsboro" => 0xdcdcdc,
+        "ghostwhite" => 0xf8f8ff,
+        "gold" => 0xffd700,
+        "goldenrod" => 0xdaa520,
+        "gray" | "grey" => 0x808080,
+        "green" => 0x008000,
+        "greenyellow" => 0xadff2f,
+        "honeydew" => 0xf0fff0,
+        "hotpink" => 0xff69b4,
+        "indianred" => 0xcd5c5c,
+        "indigo" => 0x4b0082,
+        "ivory" => 0xfffff0,
+        "khaki" => 0xf0e68c,
+        "lavender" => 0xe6e6fa,
+        "lavenderblush" => 0xfff0f5,
+        "lawngreen" => 0x7cfc00,
+        "lemonchiffon" => 0xfffacd,
+        "lightblue" => 0xadd8e6,
+        "lightcoral" => 0xf08080,
+        "lightcyan" => 0xe0ffff,
+        "lightgoldenrodyellow" => 0xfafad2,
+        "lightgray" | "lightgrey" => 0xd3d3d3,
+        "lightgreen" => 0x90ee90,
+        "lightpink" => 0xffb6c1,
+        "lightsalmon" => 0xffa07a,
+        "lightseagreen" => 0x20b2aa,
+        "lightskyblue" => 0x87cefa,
+        "lightslategray" | "lightslategrey" => 0x778899,
+        "lightsteelblue" => 0xb0c4de,
+        "lightyellow" => 0xffffe0,
+        "lime" => 0x00ff00,
+        "limegreen" => 0x32cd32,
+        "linen" => 0xfaf0e6,
+        "maroon" => 0x800000,
+        "mediumaquamarine" => 0x66cdaa,
+        "mediumblue" => 0x0000cd,
+        "mediumorchid" => 0xba55d3,
+        "mediumpurple" => 0x9370db,
+        "mediumseagreen" => 0x3cb371,
+        "mediumslateblue" => 0x7b68ee,
+        "mediumspringgreen" => 0x00fa9a,
+        "mediumturquoise" => 0x48d1cc,
+        "mediumvioletred" => 0xc71585,
+        "midnightblue" => 0x191970,
+        "mintcream" => 0xf5fffa,
+        "mistyrose" => 0xffe4e1,
+        "moccasin" => 0xffe4b5,
+        "navajowhite" => 0xffdead,
+        "navy" => 0x000080,
+        "oldlace" => 0xfdf5e6,
+        "olive" => 0x808000,
+        "olivedrab" => 0x6b8e23,
+        "orange" => 0xffa500,
+        "orangered" => 0xff4500,
+        "orchid" => 0xda70d6,
+        "palegoldenrod" => 0xeee8aa,
+        "palegreen" => 0x98fb98,
+        "paleturquoise" => 0xafeeee,
+        "palevioletred" => 0xdb7093,
+        "papayawhip" => 0xffefd5,
+        "peachpuff" => 0xffdab9,
+        "peru" => 0xcd853f,
+        "pink" => 0xffc0cb,
+        "plum" => 0xdda0dd,
+        "powderblue" => 0xb0e0e6,
+        "purple" => 0x800080,
+        "rebeccapurple" => 0x663399,
+        "red" => 0xff0000,
+        "rosybrown" => 0xbc8f8f,
+        "royalblue" => 0x4169e1,
+        "saddlebrown" => 0x8b4513,
+        "salmon" => 0xfa8072,
+        "sandybrown" => 0xf4a460,
+        "seagreen" => 0x2e8b57,
+        "seashell" => 0xfff5ee,
+        "sienna" => 0xa0522d,
+        "silver" => 0xc0c0c0,
+        "skyblue" => 0x87ceeb,
+        "slateblue" => 0x6a5acd,
+        "slategray" | "slategrey" => 0x708090,
+        "snow" => 0xfffafa,
+        "springgreen" => 0x00ff7f,
+        "steelblue" => 0x4682b4,
+        "tan" => 0xd2b48c,
+        "teal" => 0x008080,
+        "thistle" => 0xd8bfd8,
+        "tomato" => 0xff6347,
+        "turquoise" => 0x40e0d0,
+        "violet" => 0xee82ee,
+        "wheat" => 0xf5deb3,
+        "white" => 0xffffff,
+        "whitesmoke" => 0xf5f5f5,
+        "yellow" => 0xffff00,
+        "yellowgreen" => 0x9acd32,
+        _ => return None,
+    };
+    Some(Color {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+        a: 0xff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Color {
+        let mut variables = HashMap::new();
+        variables.insert("blue".to_string(), "hsl(210, 50%, 60%)".to_string());
+        variables.insert("alias".to_string(), "var(blue)".to_string());
+        parse_css_color(s, &variables).unwrap()
+    }
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    #[test]
+    fn test_parse_css_colors() {
+        assert_eq!(parse("#abc"), rgba(0xaa, 0xbb, 0xcc, 0xff));
+        assert_eq!(parse("#11223380"), rgba(0x11, 0x22, 0x33, 0x80));
+        assert_eq!(parse("rgb(255, 0, 10)"), rgba(255, 0, 10, 255));
+        assert_eq!(parse("rgba(255, 0, 10, 0.5)"), rgba(255, 0, 10, 128));
+        assert_eq!(parse("hsl(0, 100%, 50%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse("RebeccaPurple"), rgba(0x66, 0x33, 0x99, 0xff));
+        assert_eq!(parse("var(alias)"), parse("var(blue)"));
+    }
+
+    #[test]
+    fn test_color_mod_functions() {
+        assert_eq!(parse("color(#ffffff alpha(0.5))").a, 128);
+        assert_eq!(parse("color(#ffffff a(- 50%))").a, 128);
+        assert_eq!(
+            parse("color(#000000 blend(#ffffff 50%))"),
+            rgba(128, 128, 128, 255)
+        );
+        assert_eq!(parse("color(var(blue) l(100%))"), rgba(255, 255, 255, 255));
+        assert_eq!(parse("color(hsl(0, 0%, 50%) s(+ 100%))").r, 255);
+
+        let contrasted = parse("color(#777777 min-contrast(#ffffff 7))");
+        assert!(contrast_ratio(contrasted, Color::WHITE) >= 7.0);
+    }
+
+    #[test]
+    fn test_invalid_colors() {
+        let variables = HashMap::new();
+        assert!(parse_css_color("#12", &variables).is_err());
+        assert!(parse_css_color("notacolor", &variables).is_err());
+        assert!(parse_css_color("var(missing)", &variables).is_err());
+        assert!(parse_css_color("rgb(1, 2)", &variables).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Highlighting of unified diffs, with the code in each hunk highlighted in the syntax of the
+//! file it belongs to.
+
+use crate::buffer::highlight_document_buffer;
+use crate::color::blend;
+use crate::options::{LineEndings, SyntectOptions};
+use crate::render::LineOutput;
+use crate::{
+    highlight_line, highlight_line_with, highlighter_input, syntax_for, syntax_for_path,
+    syntax_set, SYNTAX_SET,
+};
+use libc::c_char;
+use std::path::Path;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, FontStyle, Highlighter, Style, Theme};
+use syntect::parsing::{Scope, SyntaxReference};
+use syntect::util::LinesWithEndings;
+
+/// Used when neither the options nor the theme give a color for added lines.
+const ADDED: Color = Color {
+    r: 0x2e,
+    g: 0xa0,
+    b: 0x43,
+    a: 0xff,
+};
+/// Used when neither the options nor the theme give a color for removed lines.
+const REMOVED: Color = Color {
+    r: 0xf8,
+    g: 0x51,
+    b: 0x49,
+    a: 0xff,
+};
+
+/// The style of the `+` or `-` marker of a changed line, drawn on the line's background.
+///
+/// The background is the configured one, the theme's background for `scope`, or a faint tint
+/// of the theme's foreground for `scope` or of `fallback`.
+fn marker_style(theme: &Theme, scope: &str, configured: Option<Color>, fallback: Color) -> Style {
+    let highlighter = Highlighter::new(theme);
+    let default = highlighter.get_default();
+    let background = theme.settings.background.unwrap_or(Color::WHITE);
+    let style = highlighter.style_for_stack(&[Scope::new(scope).unwrap()]);
+
+    let tint = match configured {
+        Some(color) => color,
+        None if style.background != default.background => style.background,
+        None => {
+            let color = if style.foreground != default.foreground {
+                style.foreground
+            } else {
+                fallback
+            };
+            Color { a: 0x40, ..color }
+        }
+    };
+    let background = blend(tint, background);
+    Style {
+        foreground: blend(style.foreground, background),
+        background,
+        font_style: FontStyle::empty(),
+    }
+}
+
+/// The old and new line counts of a hunk header such as `@@ -1,4 +1,5 @@`.
+fn hunk_lengths(line: &str) -> Option<(usize, usize)> {
+    let mut ranges = line.strip_prefix("@@ ")?.split_whitespace();
+    let old = ranges.next()?.strip_prefix('-')?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let length = |range: &str| match range.split_once(',') {
+        Some((_, length)) => length.parse().ok(),
+        None => Some(1),
+    };
+    Some((length(old)?, length(new)?))
+}
+
+/// The path named by a `---` or `+++` header, without a timestamp or `a/`/`b/` prefix.
+fn header_path<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    let path = line.strip_prefix(prefix)?;
+    let path = path.split('\t').next().unwrap_or("").trim_end();
+    Some(
+        path.strip_prefix("a/")
+            .or_else(|| path.strip_prefix("b/"))
+            .unwrap_or(path),
+    )
+}
+
+/// Highlights a unified diff.
+///
+/// Headers are highlighted with the Diff syntax. Hunk lines are highlighted with the syntax of
+/// the file named by the `+++` header, or the `---` header for deleted files, with removed
+/// and context lines forming the old version and added and context lines the new one.
+pub(crate) fn highlight_diff(
+    text: &str,
+    theme: &'static Theme,
+    options: &SyntectOptions,
+) -> Result<String, String> {
+    let ss = SYNTAX_SET.get().unwrap();
+    let line_endings = options.line_endings;
+    let plain_text = syntax_for(ss.find_syntax_plain_text(), line_endings);
+    let diff_syntax = ss
+        .find_syntax_by_extension("diff")
+        .map_or(plain_text, |syntax| syntax_for(syntax, line_endings));
+    let added = marker_style(
+        theme,
+        "markup.inserted.diff",
+        options.diff_added_background,
+        ADDED,
+    );
+    let removed = marker_style(
+        theme,
+        "markup.deleted.diff",
+        options.diff_removed_background,
+        REMOVED,
+    );
+    let context = Highlighter::new(theme).get_default();
+    let theme_background = context.background;
+
+    let mut output = LineOutput::new(theme, options);
+    output.set_whole_document(true);
+    let mut headers = HighlightLines::new(diff_syntax, theme);
+    let mut old = HighlightLines::new(plain_text, theme);
+    let mut new = HighlightLines::new(plain_text, theme);
+    let mut old_path = None;
+    // Lines left in the current hunk, on the old and on the new side.
+    let mut remaining: (usize, usize) = (0, 0);
+
+    let mut out = output.begin_document();
+    for line in LinesWithEndings::from(text) {
+        if remaining != (0, 0) {
+            let first = line.as_bytes()[0];
+            let (marker, marker_style): (&'static str, Style) = match first {
+                // Some tools strip the space off empty context lines.
+                b'\r' | b'\n' => ("", context),
+                b' ' => (" ", context),
+                b'+' => ("+", added),
+                b'-' => ("-", removed),
+                // "\ No newline at end of file" is left to the Diff syntax.
+                b'\\' => ("", context),
+                _ => {
+                    remaining = (0, 0);
+                    ("", context)
+                }
+            };
+            if remaining != (0, 0) && first != b'\\' {
+                let code = &line[marker.len()..];
+                let highlighter = match first {
+                    b'-' => {
+                        remaining.0 = remaining.0.saturating_sub(1);
+                        &mut old
+                    }
+                    b'+' => {
+                        remaining.1 = remaining.1.saturating_sub(1);
+                        &mut new
+                    }
+                    _ => {
+                        remaining = (remaining.0.saturating_sub(1), remaining.1.saturating_sub(1));
+                        advance(&mut old, code, line_endings)?;
+                        &mut new
+                    }
+                };
+                out.push_str(&highlight_line_with(
+                    highlighter,
+                    &mut output,
+                    line_endings,
+                    code,
+                    |regions| {
+                        for (style, _) in regions.iter_mut() {
+                            if style.background == theme_background {
+                                style.background = marker_style.background;
+                            }
+                        }
+                        regions.insert(0, (marker_style, marker));
+                    },
+                )?);
+                continue;
+            }
+        }
+
+        if let Some(path) = header_path(line, "--- ") {
+            old_path = Some(path);
+        } else if let Some(path) = header_path(line, "+++ ") {
+            let path = match (path, old_path) {
+                ("/dev/null", Some(old_path)) => old_path,
+                _ => path,
+            };
+            let syntax =
+                file_syntax(path).map_or(plain_text, |syntax| syntax_for(syntax, line_endings));
+            old = HighlightLines::new(syntax, theme);
+            new = HighlightLines::new(syntax, theme);
+        } else if let Some(lengths) = hunk_lengths(line) {
+            remaining = lengths;
+        }
+        out.push_str(&highlight_line(
+            &mut headers,
+            &mut output,
+            line_endings,
+            line,
+        )?);
+    }
+    output.close_document(&mut out);
+    Ok(out)
+}
+
+fn file_syntax(path: &str) -> Option<&'static SyntaxReference> {
+    syntax_for_path(SYNTAX_SET.get().unwrap(), Path::new(path))
+}
+
+/// Feeds a line to a highlighter without rendering it, to keep its state in step.
+fn advance(
+    highlighter: &mut HighlightLines<'static>,
+    line: &str,
+    line_endings: LineEndings,
+) -> Result<(), String> {
+    let (input, _) = highlighter_input(line, line_endings);
+    highlighter
+        .highlight_line(&input, syntax_set(line_endings))
+        .map(|_| ())
+        .map_err(|err| format!("Highlighting error: {}", err))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_diff_highlight(
+    data: *const c_char,
+    len: usize,
+    theme_name: *const c_char,
+    options: *const SyntectOptions,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+) -> *const c_char {
+    highlight_document_buffer(
+        data,
+        len,
+        theme_name,
+        options,
+        out_len,
+        error,
+        "Invalid diff text",
+        highlight_diff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::OutputFormat;
+    use crate::{get_syntax_and_theme, initialize};
+
+    const DIFF: &str = "diff --git a/src/main.rs b/src/main.rs\n\
+        --- a/src/main.rs\n\
+        +++ b/src/main.rs\n\
+        @@ -1,3 +1,3 @@\n\
+        \x20fn main() {\n\
+        -    let x = 1;\n\
+        +    let x = \"two\";\n\
+        \x20}\n\
+        diff --git a/notes.txt b/notes.txt\n";
+
+    #[test]
+    fn test_hunk_lengths() {
+        assert_eq!(hunk_lengths("@@ -1,3 +1,4 @@ fn main"), Some((3, 4)));
+        assert_eq!(hunk_lengths("@@ -1 +0,0 @@"), Some((1, 0)));
+        assert_eq!(hunk_lengths("@@@ -1 -1 +1 @@@"), None);
+    }
+
+    #[test]
+    fn test_highlight_diff() {
+        initialize();
+        let (_, theme, _) = get_syntax_and_theme("base16-ocean.dark").unwrap();
+        let options = SyntectOptions {
+            format: OutputFormat::Html,
+            diff_added_background: Some(Color {
+                r: 0,
+                g: 0x40,
+                b: 0,
+                a: 0xff,
+            }),
+            ..SyntectOptions::default()
+        };
+
+        let html = highlight_diff(DIFF, theme, &options).unwrap();
+        let lines: Vec<&str> = html.lines().collect();
+        assert_eq!(lines.len(), DIFF.lines().count() + 2);
+        // Code in the hunk is highlighted as Rust: `let` is a storage keyword.
+        assert!(
+            lines[6].contains("color:#b48ead;\">let</span>"),
+            "{}",
+            lines[6]
+        );
+        assert!(lines[7].contains("background-color:#004000;color:#b48ead;\">let</span>"));
+        assert!(lines[7].contains(">+</span>"));
+        // The removed line gets a red tint.
+        assert!(
+            lines[6].contains("background-color:#503c46;"),
+            "{}",
+            lines[6]
+        );
+        // The second file header ends the hunk.
+        assert!(lines[9].contains(">diff --git a/notes.txt b/notes.txt</span>"));
+
+        // Stripped line endings leave one row per line of the diff.
+        let strip = SyntectOptions {
+            line_endings: LineEndings::Strip,
+            ..options
+        };
+        let stripped = highlight_diff(DIFF, theme, &strip).unwrap();
+        assert_eq!(stripped.lines().count(), lines.len());
+        assert!(stripped
+            .lines()
+            .nth(9)
+            .unwrap()
+            .contains(">diff --git a/notes.txt"));
+    }
+}
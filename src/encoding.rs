@@ -0,0 +1,162 @@
+//! Decoding of highlighted files into UTF-8 lines.
+
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::io::{self, BufRead};
+
+/// How many bytes at the start of a file are checked for NUL characters.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Resolves an encoding label such as `"latin1"` or `"Shift_JIS"`.
+pub(crate) fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+fn is_utf16(encoding: &'static Encoding) -> bool {
+    encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+/// Decodes the start of a file well enough to pick a syntax from its first line.
+pub(crate) fn first_line(bytes: &[u8]) -> String {
+    let (text, _, _) = UTF_8.decode(bytes);
+    text.lines().next().unwrap_or("").to_string()
+}
+
+/// Reads lines of text out of a byte stream in any encoding.
+///
+/// Without an explicit encoding the stream is read as UTF-8 unless it starts with a UTF-8 or
+/// UTF-16 byte order mark. Malformed sequences become U+FFFD instead of failing.
+pub(crate) struct LineDecoder {
+    encoding: Option<&'static Encoding>,
+    decoder: Option<Decoder>,
+    pending: String,
+    finished: bool,
+}
+
+impl LineDecoder {
+    pub(crate) fn new(encoding: Option<&'static Encoding>) -> LineDecoder {
+        LineDecoder {
+            encoding,
+            decoder: None,
+            pending: String::new(),
+            finished: false,
+        }
+    }
+
+    /// Changes the source encoding. Only takes effect before the first line is read.
+    pub(crate) fn set_encoding(&mut self, encoding: Option<&'static Encoding>) {
+        if self.decoder.is_none() {
+            self.encoding = encoding;
+        }
+    }
+
+    /// Returns the next line including its `\n`, or `None` at the end of the stream.
+    pub(crate) fn read_line(&mut self, reader: &mut impl BufRead) -> io::Result<Option<String>> {
+        loop {
+            if let Some(end) = self.pending.find('\n') {
+                return Ok(Some(self.pending.drain(..=end).collect()));
+            }
+            if self.finished {
+                return Ok((!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending)));
+            }
+
+            let bytes = reader.fill_buf()?;
+            let decoder = match &mut self.decoder {
+                Some(decoder) => decoder,
+                None => {
+                    let decoder = start_decoding(bytes, self.encoding)?;
+                    self.decoder.insert(decoder)
+                }
+            };
+            let last = bytes.is_empty();
+            decode(decoder, bytes, &mut self.pending, last);
+            let consumed = bytes.len();
+            reader.consume(consumed);
+            self.finished = last;
+        }
+    }
+}
+
+/// Creates the decoder for a stream starting with `bytes`, refusing binary data.
+fn start_decoding(bytes: &[u8], encoding: Option<&'static Encoding>) -> io::Result<Decoder> {
+    let sniffed = Encoding::for_bom(bytes).map(|(encoding, _)| encoding);
+    let utf16 = sniffed.or(encoding).is_some_and(is_utf16);
+    let head = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if !utf16 && head.contains(&0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "File appears to be binary",
+        ));
+    }
+
+    Ok(match encoding {
+        // A byte order mark overrides the default, but not an explicit encoding.
+        Some(encoding) => encoding.new_decoder_with_bom_removal(),
+        None => UTF_8.new_decoder(),
+    })
+}
+
+fn decode(decoder: &mut Decoder, mut bytes: &[u8], out: &mut String, last: bool) {
+    loop {
+        if let Some(needed) = decoder.max_utf8_buffer_length(bytes.len()) {
+            out.reserve(needed);
+        }
+        let (result, read, _) = decoder.decode_to_string(bytes, out, last);
+        bytes = &bytes[read..];
+        match result {
+            CoderResult::InputEmpty => return,
+            CoderResult::OutputFull => out.reserve(bytes.len().max(16) * 3),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    fn read_all(bytes: &[u8], encoding: Option<&'static Encoding>) -> io::Result<Vec<String>> {
+        // A tiny buffer makes multi-byte sequences straddle reads.
+        let mut reader = BufReader::with_capacity(3, bytes);
+        let mut decoder = LineDecoder::new(encoding);
+        let mut lines = Vec::new();
+        while let Some(line) = decoder.read_line(&mut reader)? {
+            lines.push(line);
+        }
+        Ok(lines)
+    }
+
+    #[test]
+    fn test_utf8_and_lossy_lines() {
+        assert_eq!(
+            read_all("a\u{e9}\nb".as_bytes(), None).unwrap(),
+            ["a\u{e9}\n", "b"]
+        );
+        assert_eq!(
+            read_all(b"caf\xe9\nok\n", None).unwrap(),
+            ["caf\u{fffd}\n", "ok\n"]
+        );
+        assert_eq!(read_all(b"\xef\xbb\xbfx\n", None).unwrap(), ["x\n"]);
+    }
+
+    #[test]
+    fn test_bom_and_explicit_encodings() {
+        let utf16le = b"\xff\xfea\x00\n\x00b\x00";
+        assert_eq!(read_all(utf16le, None).unwrap(), ["a\n", "b"]);
+        let utf16be = b"\xfe\xff\x00a\x00\n";
+        assert_eq!(read_all(utf16be, None).unwrap(), ["a\n"]);
+
+        let latin1 = encoding_for_label("latin1");
+        assert_eq!(read_all(b"caf\xe9\n", latin1).unwrap(), ["caf\u{e9}\n"]);
+        let shift_jis = encoding_for_label("Shift_JIS");
+        assert_eq!(read_all(b"\x82\xa0\n", shift_jis).unwrap(), ["\u{3042}\n"]);
+        assert!(encoding_for_label("no-such-encoding").is_none());
+    }
+
+    #[test]
+    fn test_binary_refused() {
+        let err = read_all(b"\x00asm\x01\x00\x00\x00", None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // UTF-16 text is full of NULs but is not binary.
+        assert!(read_all(b"a\x00\n\x00", Some(UTF_16LE)).is_ok());
+    }
+}
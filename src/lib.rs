@@ -1,77 +1,491 @@
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 extern crate libc;
 extern crate syntect;
 
 use libc::c_char;
+use std::borrow::Cow;
 use std::ffi::{CStr, CString};
-use std::io::BufRead;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 use std::ptr;
-use std::sync::Once;
-use syntect::dumps::{dump_to_file, from_dump_file};
+use std::sync::{Arc, OnceLock};
 use syntect::easy::{HighlightFile, HighlightLines};
 use syntect::highlighting::{Style, Theme, ThemeSet};
-use syntect::parsing::SyntaxSet;
-use syntect::util::as_24_bit_terminal_escaped;
-
-static INIT: Once = Once::new();
-static mut THEME_SET: Option<ThemeSet> = None;
-static mut SYNTAX_SET: Option<SyntaxSet> = None;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use walkdir::WalkDir;
+
+mod base16;
+mod buffer;
+mod color;
+mod diff;
+mod encoding;
+mod markdown;
+mod options;
+mod render;
+mod search;
+mod spans;
+mod sublime_scheme;
+mod theme_analysis;
+mod theme_builder;
+mod theme_cache;
+mod theme_export;
+mod theme_override;
+mod theme_registry;
+mod theme_set;
+mod vscode_theme;
+
+use encoding::LineDecoder;
+use options::{LineEndings, SyntectOptions};
+use render::LineOutput;
+use search::Matches;
+use spans::SyntectSpan;
+use sublime_scheme::load_sublime_color_scheme;
+
+static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+static SYNTAX_SET_NONEWLINES: OnceLock<SyntaxSet> = OnceLock::new();
 
 fn initialize() {
-    INIT.call_once(|| {
-        let ts = ThemeSet::load_defaults();
-        let ss = SyntaxSet::load_defaults_newlines();
-        unsafe {
-            THEME_SET = Some(ts);
-            SYNTAX_SET = Some(ss);
-        }
-    });
+    THEME_SET.get_or_init(ThemeSet::load_defaults);
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines);
 }
 
-fn get_syntax_and_theme(theme_name: &str) -> Result<(&'static SyntaxSet, &'static Theme), String> {
-    unsafe {
-        let ss = SYNTAX_SET
-            .as_ref()
-            .ok_or_else(|| "SyntaxSet not initialized".to_string())?;
-        let ts = THEME_SET
-            .as_ref()
-            .ok_or_else(|| "ThemeSet not initialized".to_string())?;
-        let theme = ts
-            .themes
-            .get(theme_name)
-            .ok_or_else(|| format!("Theme '{}' not found", theme_name))?;
-        Ok((ss, theme))
+/// Looks up a theme by name, trying the registered themes before the default ones.
+///
+/// A registered theme is returned along with the `Arc` that keeps it alive; the caller must
+/// hold on to it for as long as it uses the theme reference.
+fn get_syntax_and_theme(
+    theme_name: &str,
+) -> Result<(&'static SyntaxSet, &'static Theme, Option<Arc<Theme>>), String> {
+    let ss = SYNTAX_SET
+        .get()
+        .ok_or_else(|| "SyntaxSet not initialized".to_string())?;
+    if let Some(theme) = theme_registry::registered_theme(theme_name) {
+        // The theme lives as long as the returned `Arc`.
+        let theme_ref = unsafe { &*Arc::as_ptr(&theme) };
+        return Ok((ss, theme_ref, Some(theme)));
     }
+    let ts = THEME_SET
+        .get()
+        .ok_or_else(|| "ThemeSet not initialized".to_string())?;
+    let theme = ts
+        .themes
+        .get(theme_name)
+        .ok_or_else(|| format!("Theme '{}' not found", theme_name))?;
+    Ok((ss, theme, None))
 }
 
 fn load_theme(tm_file: &str, enable_caching: bool) -> Result<Theme, String> {
-    let tm_path = Path::new(tm_file);
+    load_theme_with(Path::new(tm_file), enable_caching, read_theme)
+}
 
+/// Loads a theme with `read`, going through the theme cache when `enable_caching` is set.
+fn load_theme_with(
+    tm_path: &Path,
+    enable_caching: bool,
+    read: fn(&Path) -> Result<Theme, String>,
+) -> Result<Theme, String> {
     if enable_caching {
-        let tm_cache = tm_path.with_extension("tmdump");
+        theme_cache::load_cached(tm_path, read)
+    } else {
+        read(tm_path)
+    }
+}
 
-        if tm_cache.exists() {
-            from_dump_file(tm_cache).map_err(|e| format!("Error loading from cache: {}", e))
-        } else {
-            let theme =
-                ThemeSet::get_theme(tm_path).map_err(|e| format!("Error loading theme: {}", e))?;
-            dump_to_file(&theme, tm_cache)
-                .map_err(|e| format!("Error dumping theme to cache: {}", e))?;
-            Ok(theme)
-        }
+/// Reads a theme file, choosing the parser from its extension.
+fn read_theme(path: &Path) -> Result<Theme, String> {
+    if is_sublime_color_scheme(path) {
+        load_sublime_color_scheme(path)
     } else {
-        ThemeSet::get_theme(tm_path).map_err(|e| format!("Error loading theme: {}", e))
+        ThemeSet::get_theme(path).map_err(|e| format!("Error loading theme: {}", e))
+    }
+}
+
+fn is_sublime_color_scheme(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("sublime-color-scheme"))
+}
+
+/// Loads every `.tmTheme` and `.sublime-color-scheme` file below `folder`, keyed by file stem.
+/// A `.tmTheme` file wins over a `.sublime-color-scheme` file with the same stem.
+fn load_theme_set_from_folder(folder: &str, enable_caching: bool) -> Result<ThemeSet, String> {
+    let mut paths = ThemeSet::discover_theme_paths(folder).map_err(|e| e.to_string())?;
+    for entry in WalkDir::new(folder).follow_links(true) {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if entry.path().is_file() && is_sublime_color_scheme(entry.path()) {
+            paths.push(entry.into_path());
+        }
+    }
+
+    let mut theme_set = ThemeSet::new();
+    for path in paths {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Invalid theme path: {}", path.display()))?;
+        // The tmTheme files come first, so a name already taken belongs to one of them.
+        if is_sublime_color_scheme(&path) && theme_set.themes.contains_key(name) {
+            continue;
+        }
+        let theme = load_theme_with(&path, enable_caching, read_theme)?;
+        theme_set.themes.insert(name.to_string(), theme);
+    }
+
+    Ok(theme_set)
+}
+
+/// Hands rendered output to C, or reports an error if it cannot be a C string.
+pub(crate) fn output_string(output: String, error: *mut *const c_char) -> *const c_char {
+    match CString::new(output) {
+        Ok(output) => output.into_raw(),
+        Err(_) => {
+            set_error(error, "Output contains a NUL character");
+            ptr::null()
+        }
+    }
+}
+
+/// Hands C what the output format puts before or after the lines highlighted one by one.
+fn document_part(
+    output: &mut LineOutput,
+    part: fn(&mut LineOutput) -> String,
+    error: *mut *const c_char,
+) -> *const c_char {
+    match output.check_line_by_line() {
+        Ok(()) => output_string(part(output), error),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+/// Stores `message` in `*error` as a C string that the caller frees with `syntect_free_string`.
+pub(crate) fn set_error(error: *mut *const c_char, message: impl Into<String>) {
+    if error.is_null() {
+        return;
+    }
+    let message = message.into().replace('\0', "");
+    unsafe {
+        *error = CString::new(message).unwrap().into_raw();
+    }
+}
+
+/// Converts a required C string argument, reporting `message` through `error` if it is
+/// NULL or not valid UTF-8.
+pub(crate) fn str_arg<'a>(
+    s: *const c_char,
+    message: &str,
+    error: *mut *const c_char,
+) -> Option<&'a str> {
+    match opt_str_arg(s, message, error) {
+        Ok(Some(s)) => Some(s),
+        Ok(None) => {
+            set_error(error, message);
+            None
+        }
+        Err(()) => None,
+    }
+}
+
+/// Converts an optional C string argument. NULL maps to `Ok(None)`.
+pub(crate) fn opt_str_arg<'a>(
+    s: *const c_char,
+    message: &str,
+    error: *mut *const c_char,
+) -> Result<Option<&'a str>, ()> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    match unsafe { CStr::from_ptr(s) }.to_str() {
+        Ok(s) => Ok(Some(s)),
+        Err(_) => {
+            set_error(error, message);
+            Err(())
+        }
+    }
+}
+
+/// Finds a syntax by the file name or extension of a path.
+fn syntax_for_path<'a>(ss: &'a SyntaxSet, path: &Path) -> Option<&'a SyntaxReference> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+    ss.find_syntax_by_extension(file_name)
+        .or_else(|| ss.find_syntax_by_extension(extension))
+}
+
+/// Opens a file for highlighting, picking its syntax by file name or, failing that, by its
+/// first line. Unlike `HighlightFile::new` this accepts files that are not valid UTF-8.
+fn open_file(
+    path: &str,
+    ss: &'static SyntaxSet,
+) -> io::Result<(BufReader<File>, &'static SyntaxReference)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let syntax = match syntax_for_path(ss, Path::new(path)) {
+        Some(syntax) => syntax,
+        None => {
+            let first_line = encoding::first_line(reader.fill_buf()?);
+            ss.find_syntax_by_first_line(&first_line)
+                .unwrap_or_else(|| ss.find_syntax_plain_text())
+        }
+    };
+
+    Ok((reader, syntax))
+}
+
+/// Returns the syntax set for lines passed with their line endings, or the one for lines
+/// without them.
+fn syntax_set(line_endings: LineEndings) -> &'static SyntaxSet {
+    match line_endings {
+        LineEndings::Strip => {
+            SYNTAX_SET_NONEWLINES.get_or_init(SyntaxSet::load_defaults_nonewlines)
+        }
+        LineEndings::Preserve | LineEndings::Normalize => SYNTAX_SET.get().unwrap(),
     }
 }
 
+/// Finds the syntax matching `syntax` in the syntax set used for `line_endings`.
+fn syntax_for(syntax: &SyntaxReference, line_endings: LineEndings) -> &'static SyntaxReference {
+    let ss = syntax_set(line_endings);
+    ss.find_syntax_by_name(&syntax.name)
+        .unwrap_or_else(|| ss.find_syntax_plain_text())
+}
+
+/// Splits a line into its content and its `\n` or `\r\n` ending.
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let content = line
+        .strip_suffix('\n')
+        .map_or(line, |line| line.strip_suffix('\r').unwrap_or(line));
+    line.split_at(content.len())
+}
+
+/// Splits the line ending off a line and returns the text to give the highlighter: the line
+/// with a plain `\n` ending, or with none in `LineEndings::Strip` mode, so a `\r` never ends up
+/// in a token.
+fn highlighter_input(line: &str, line_endings: LineEndings) -> (Cow<'_, str>, &str) {
+    let (content, line_ending) = split_line_ending(line);
+    let input = match line_endings {
+        LineEndings::Strip => Cow::Borrowed(content),
+        _ if line_ending == "\r\n" => Cow::Owned(format!("{}\n", content)),
+        _ => Cow::Borrowed(line),
+    };
+    (input, line_ending)
+}
+
+/// Highlights one line and renders it.
+fn highlight_line(
+    highlighter: &mut HighlightLines<'static>,
+    output: &mut LineOutput,
+    line_endings: LineEndings,
+    line: &str,
+) -> Result<String, String> {
+    highlight_line_with(highlighter, output, line_endings, line, |_| {})
+}
+
+/// Like `highlight_line`, letting `edit` change the styled regions before they are rendered.
+fn highlight_line_with(
+    highlighter: &mut HighlightLines<'static>,
+    output: &mut LineOutput,
+    line_endings: LineEndings,
+    line: &str,
+    edit: impl for<'a> FnOnce(&mut Vec<(Style, &'a str)>),
+) -> Result<String, String> {
+    let (_, line_ending) = split_line_ending(line);
+    highlight_regions(highlighter, line_endings, line, |mut regions| {
+        edit(&mut regions);
+        output.render_line(&regions, line_ending)
+    })
+}
+
+/// Highlights one line and hands its styled regions to `f`.
+fn highlight_regions<R>(
+    highlighter: &mut HighlightLines<'static>,
+    line_endings: LineEndings,
+    line: &str,
+    f: impl for<'a> FnOnce(Vec<(Style, &'a str)>) -> R,
+) -> Result<R, String> {
+    let (input, _) = highlighter_input(line, line_endings);
+    let regions: Vec<(Style, &str)> = highlighter
+        .highlight_line(&input, syntax_set(line_endings))
+        .map_err(|err| format!("Highlighting error: {}", err))?;
+    Ok(f(regions))
+}
+
+/// Highlights and renders one line of a file or text, emphasizing its search matches.
+fn render_matched_line(
+    highlighter: &mut HighlightLines<'static>,
+    output: &mut LineOutput,
+    matches: &mut Matches,
+    line_endings: LineEndings,
+    line: &str,
+) -> Result<String, String> {
+    output.check_line_by_line()?;
+    let ranges = matches.take_ranges(split_line_ending(line).0);
+    let matches = &*matches;
+    highlight_line_with(highlighter, output, line_endings, line, |regions| {
+        matches.overlay(regions, &ranges)
+    })
+}
+
+/// Highlights one line of a file or text into spans, emphasizing its search matches.
+fn matched_line_spans(
+    highlighter: &mut HighlightLines<'static>,
+    matches: &mut Matches,
+    line_endings: LineEndings,
+    line: &str,
+) -> Result<Vec<SyntectSpan>, String> {
+    let (content, _) = split_line_ending(line);
+    let ranges = matches.take_ranges(content);
+    let matches = &*matches;
+    highlight_regions(highlighter, line_endings, line, |mut regions| {
+        matches.overlay(&mut regions, &ranges);
+        spans::to_spans(&regions, content.len(), &ranges)
+    })
+}
+
 #[repr(C)]
 pub struct SyntectFile {
     highlighter: HighlightFile<'static>,
+    decoder: LineDecoder,
+    syntax: &'static SyntaxReference,
+    line_endings: LineEndings,
+    matches: Matches,
+    theme: &'static Theme,
+    output: LineOutput,
+    // Keeps a registered theme alive; declared after `highlighter` so it is dropped last.
+    _theme: Option<Arc<Theme>>,
 }
 
 #[repr(C)]
 pub struct SyntectLines {
     highlighter: HighlightLines<'static>,
+    syntax: &'static SyntaxReference,
+    line_endings: LineEndings,
+    matches: Matches,
+    theme: &'static Theme,
+    output: LineOutput,
+    // Keeps a registered theme alive; declared after `highlighter` so it is dropped last.
+    _theme: Option<Arc<Theme>>,
+}
+
+impl SyntectFile {
+    fn new(
+        reader: BufReader<File>,
+        syntax: &'static SyntaxReference,
+        theme: &'static Theme,
+        registered: Option<Arc<Theme>>,
+    ) -> SyntectFile {
+        SyntectFile {
+            highlighter: HighlightFile {
+                reader,
+                highlight_lines: HighlightLines::new(syntax, theme),
+            },
+            decoder: LineDecoder::new(None),
+            syntax,
+            line_endings: LineEndings::Preserve,
+            matches: Matches::default(),
+            theme,
+            output: LineOutput::new(theme, &SyntectOptions::default()),
+            _theme: registered,
+        }
+    }
+
+    pub(crate) fn set_options(&mut self, options: &SyntectOptions) {
+        self.decoder.set_encoding(options.encoding);
+        if options.line_endings.strips() != self.line_endings.strips() {
+            self.syntax = syntax_for(self.syntax, options.line_endings);
+            self.highlighter.highlight_lines = HighlightLines::new(self.syntax, self.theme);
+        }
+        self.line_endings = options.line_endings;
+        self.matches.set_options(options);
+        self.output = LineOutput::new(self.theme, options);
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>, String> {
+        self.decoder
+            .read_line(&mut self.highlighter.reader)
+            .map_err(|err| format!("Failed to read file: {}", err))
+    }
+
+    /// Reads and highlights the next line of the file, or returns `None` at its end.
+    pub(crate) fn highlight_next_line(&mut self) -> Result<Option<String>, String> {
+        let Some(line) = self.read_line()? else {
+            return Ok(None);
+        };
+        render_matched_line(
+            &mut self.highlighter.highlight_lines,
+            &mut self.output,
+            &mut self.matches,
+            self.line_endings,
+            &line,
+        )
+        .map(Some)
+    }
+
+    /// Reads the next line of the file and highlights it into spans.
+    pub(crate) fn highlight_next_line_spans(&mut self) -> Result<Option<Vec<SyntectSpan>>, String> {
+        let Some(line) = self.read_line()? else {
+            return Ok(None);
+        };
+        matched_line_spans(
+            &mut self.highlighter.highlight_lines,
+            &mut self.matches,
+            self.line_endings,
+            &line,
+        )
+        .map(Some)
+    }
+}
+
+impl SyntectLines {
+    fn new(
+        syntax: &'static SyntaxReference,
+        theme: &'static Theme,
+        registered: Option<Arc<Theme>>,
+    ) -> SyntectLines {
+        SyntectLines {
+            highlighter: HighlightLines::new(syntax, theme),
+            syntax,
+            line_endings: LineEndings::Preserve,
+            matches: Matches::default(),
+            theme,
+            output: LineOutput::new(theme, &SyntectOptions::default()),
+            _theme: registered,
+        }
+    }
+
+    pub(crate) fn set_options(&mut self, options: &SyntectOptions) {
+        if options.line_endings.strips() != self.line_endings.strips() {
+            self.syntax = syntax_for(self.syntax, options.line_endings);
+            self.highlighter = HighlightLines::new(self.syntax, self.theme);
+        }
+        self.line_endings = options.line_endings;
+        self.matches.set_options(options);
+        self.output = LineOutput::new(self.theme, options);
+    }
+
+    /// Highlights the next line of text, continuing from the state left by the previous one.
+    pub(crate) fn highlight_line(&mut self, line: &str) -> Result<String, String> {
+        render_matched_line(
+            &mut self.highlighter,
+            &mut self.output,
+            &mut self.matches,
+            self.line_endings,
+            line,
+        )
+    }
+
+    /// Highlights the next line of text into spans.
+    pub(crate) fn highlight_line_spans(&mut self, line: &str) -> Result<Vec<SyntectSpan>, String> {
+        matched_line_spans(
+            &mut self.highlighter,
+            &mut self.matches,
+            self.line_endings,
+            line,
+        )
+    }
 }
 
 #[repr(C)]
@@ -95,17 +509,17 @@ pub extern "C" fn syntect_create_file(
     let path = unsafe {
         CStr::from_ptr(path).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid path").unwrap().into_raw();
-            return "";
+            ""
         })
     };
     let theme_name = unsafe {
         CStr::from_ptr(theme_name).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid theme name").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
-    let (ss, theme) = match get_syntax_and_theme(theme_name) {
+    let (ss, theme, registered) = match get_syntax_and_theme(theme_name) {
         Ok(result) => result,
         Err(err) => {
             unsafe {
@@ -115,8 +529,8 @@ pub extern "C" fn syntect_create_file(
         }
     };
 
-    let highlighter = match HighlightFile::new(path, ss, theme) {
-        Ok(highlighter) => highlighter,
+    let (reader, syntax) = match open_file(path, ss) {
+        Ok(opened) => opened,
         Err(err) => {
             unsafe {
                 *error = CString::new(format!("Failed to create HighlightFile: {}", err))
@@ -127,7 +541,9 @@ pub extern "C" fn syntect_create_file(
         }
     };
 
-    Box::into_raw(Box::new(SyntectFile { highlighter }))
+    Box::into_raw(Box::new(SyntectFile::new(
+        reader, syntax, theme, registered,
+    )))
 }
 
 #[no_mangle]
@@ -141,17 +557,15 @@ pub extern "C" fn syntect_create_file_with_theme(
     let path = unsafe {
         CStr::from_ptr(path).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid path").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
     let theme = unsafe { &(*theme).theme };
 
-    let ss = unsafe {
-        SYNTAX_SET
-            .as_ref()
-            .ok_or_else(|| "SyntaxSet not initialized".to_string())
-    };
+    let ss = SYNTAX_SET
+        .get()
+        .ok_or_else(|| "SyntaxSet not initialized".to_string());
 
     let ss = match ss {
         Ok(s) => s,
@@ -163,8 +577,8 @@ pub extern "C" fn syntect_create_file_with_theme(
         }
     };
 
-    let highlighter = match HighlightFile::new(path, ss, theme) {
-        Ok(highlighter) => highlighter,
+    let (reader, syntax) = match open_file(path, ss) {
+        Ok(opened) => opened,
         Err(err) => {
             unsafe {
                 *error = CString::new(format!("Failed to create HighlightFile: {}", err))
@@ -175,7 +589,7 @@ pub extern "C" fn syntect_create_file_with_theme(
         }
     };
 
-    Box::into_raw(Box::new(SyntectFile { highlighter }))
+    Box::into_raw(Box::new(SyntectFile::new(reader, syntax, theme, None)))
 }
 
 #[no_mangle]
@@ -188,29 +602,40 @@ pub extern "C" fn syntect_highlight_file_line(
         &mut *wrapper
     };
 
-    let mut line = String::new();
-    if wrapper.highlighter.reader.read_line(&mut line).unwrap() > 0 {
-        let regions: Vec<(Style, &str)> = match wrapper
-            .highlighter
-            .highlight_lines
-            .highlight_line(&line, unsafe { SYNTAX_SET.as_ref().unwrap() })
-        {
-            Ok(regions) => regions,
-            Err(err) => {
-                unsafe {
-                    *error = CString::new(format!("Highlighting error: {}", err))
-                        .unwrap()
-                        .into_raw();
-                }
-                return ptr::null();
-            }
-        };
-        let highlighted_line = as_24_bit_terminal_escaped(&regions[..], true);
-        let c_highlighted_line = CString::new(highlighted_line).unwrap();
-        return c_highlighted_line.into_raw();
+    match wrapper.highlight_next_line() {
+        Ok(Some(highlighted_line)) => output_string(highlighted_line, error),
+        Ok(None) => ptr::null(),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
     }
+}
 
-    ptr::null()
+#[no_mangle]
+pub extern "C" fn syntect_file_begin_document(
+    wrapper: *mut SyntectFile,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+
+    document_part(&mut wrapper.output, LineOutput::begin_document, error)
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_file_end_document(
+    wrapper: *mut SyntectFile,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+
+    document_part(&mut wrapper.output, LineOutput::end_document, error)
 }
 
 #[no_mangle]
@@ -232,11 +657,11 @@ pub extern "C" fn syntect_create_lines(
     let theme_name = unsafe {
         CStr::from_ptr(theme_name).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid theme name").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
-    let (ss, theme) = match get_syntax_and_theme(theme_name) {
+    let (ss, theme, registered) = match get_syntax_and_theme(theme_name) {
         Ok(result) => result,
         Err(err) => {
             unsafe {
@@ -258,9 +683,7 @@ pub extern "C" fn syntect_create_lines(
         }
     };
 
-    let highlighter = HighlightLines::new(syntax, theme);
-
-    Box::into_raw(Box::new(SyntectLines { highlighter }))
+    Box::into_raw(Box::new(SyntectLines::new(syntax, theme, registered)))
 }
 
 #[no_mangle]
@@ -272,11 +695,9 @@ pub extern "C" fn syntect_create_lines_with_theme(
 
     let theme = unsafe { &(*theme).theme };
 
-    let ss = unsafe {
-        SYNTAX_SET
-            .as_ref()
-            .ok_or_else(|| "SyntaxSet not initialized".to_string())
-    };
+    let ss = SYNTAX_SET
+        .get()
+        .ok_or_else(|| "SyntaxSet not initialized".to_string());
 
     let ss = match ss {
         Ok(s) => s,
@@ -300,9 +721,7 @@ pub extern "C" fn syntect_create_lines_with_theme(
         }
     };
 
-    let highlighter = HighlightLines::new(syntax, theme);
-
-    Box::into_raw(Box::new(SyntectLines { highlighter }))
+    Box::into_raw(Box::new(SyntectLines::new(syntax, theme, None)))
 }
 
 #[no_mangle]
@@ -315,36 +734,43 @@ pub extern "C" fn syntect_highlight_text_line(
         assert!(!wrapper.is_null());
         &mut *wrapper
     };
-
-    let c_str = unsafe { CStr::from_ptr(line) };
-    let line = match c_str.to_str() {
-        Ok(str) => str,
-        Err(_) => {
-            unsafe {
-                *error = CString::new("Invalid input line").unwrap().into_raw();
-            }
-            return ptr::null();
-        }
+    let Some(line) = str_arg(line, "Invalid input line", error) else {
+        return ptr::null();
     };
 
-    let ranges: Vec<(Style, &str)> = match wrapper
-        .highlighter
-        .highlight_line(line, unsafe { SYNTAX_SET.as_ref().unwrap() })
-    {
-        Ok(ranges) => ranges,
+    match wrapper.highlight_line(line) {
+        Ok(highlighted_line) => output_string(highlighted_line, error),
         Err(err) => {
-            unsafe {
-                *error = CString::new(format!("Highlighting error: {}", err))
-                    .unwrap()
-                    .into_raw();
-            }
-            return ptr::null();
+            set_error(error, err);
+            ptr::null()
         }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_lines_begin_document(
+    wrapper: *mut SyntectLines,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
     };
 
-    let highlighted_line = as_24_bit_terminal_escaped(&ranges[..], true);
-    let c_highlighted_line = CString::new(highlighted_line).unwrap();
-    c_highlighted_line.into_raw()
+    document_part(&mut wrapper.output, LineOutput::begin_document, error)
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_lines_end_document(
+    wrapper: *mut SyntectLines,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+
+    document_part(&mut wrapper.output, LineOutput::end_document, error)
 }
 
 #[no_mangle]
@@ -383,11 +809,11 @@ pub extern "C" fn syntect_load_theme_set_from_folder(
     let folder = unsafe {
         CStr::from_ptr(folder).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid folder path").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
-    match ThemeSet::load_from_folder(folder) {
+    match load_theme_set_from_folder(folder, false) {
         Ok(theme_set) => Box::into_raw(Box::new(SyntectThemeSet { themes: theme_set })),
         Err(err) => {
             unsafe {
@@ -400,6 +826,25 @@ pub extern "C" fn syntect_load_theme_set_from_folder(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn syntect_load_theme_set_from_folder_with_caching(
+    folder: *const c_char,
+    enable_caching: bool,
+    error: *mut *const c_char,
+) -> *mut SyntectThemeSet {
+    let Some(folder) = str_arg(folder, "Invalid folder path", error) else {
+        return ptr::null_mut();
+    };
+
+    match load_theme_set_from_folder(folder, enable_caching) {
+        Ok(theme_set) => Box::into_raw(Box::new(SyntectThemeSet { themes: theme_set })),
+        Err(err) => {
+            set_error(error, format!("Failed to load themes from folder: {}", err));
+            ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn syntect_get_theme_names(
     theme_set: *const SyntectThemeSet,
@@ -468,7 +913,7 @@ pub extern "C" fn syntect_load_theme(
     let theme_path = unsafe {
         CStr::from_ptr(theme_path).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid theme path").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
@@ -497,7 +942,7 @@ pub extern "C" fn syntect_get_theme_from_set(
     let theme_name = unsafe {
         CStr::from_ptr(theme_name).to_str().unwrap_or_else(|_| {
             *error = CString::new("Invalid theme name").unwrap().into_raw();
-            return "";
+            ""
         })
     };
 
@@ -559,6 +1004,7 @@ pub extern "C" fn syntect_free_string(s: *mut c_char) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use libc::c_int;
     use std::ffi::CString;
 
     #[test]
@@ -584,6 +1030,73 @@ mod tests {
         syntect_free_file(wrapper);
     }
 
+    #[test]
+    fn test_highlight_file_encodings() {
+        let dir = std::env::temp_dir().join("syntect-c-test-encodings");
+        std::fs::create_dir_all(&dir).unwrap();
+        let text_path = dir.join("latin1.c");
+        std::fs::write(&text_path, b"/* caf\xe9 */\n").unwrap();
+        let binary_path = dir.join("binary.c");
+        std::fs::write(&binary_path, b"\x7fELF\x02\x01\x01\x00\x00\n").unwrap();
+        let theme_name = CString::new("base16-ocean.dark").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let highlight_first_line = |path: &Path, encoding: Option<&str>| {
+            let path = CString::new(path.to_str().unwrap()).unwrap();
+            let mut error: *const c_char = ptr::null();
+            let file = syntect_create_file(path.as_ptr(), theme_name.as_ptr(), &mut error);
+            assert!(!file.is_null());
+            if let Some(encoding) = encoding {
+                let options = options::syntect_options_new();
+                let encoding = CString::new(encoding).unwrap();
+                assert!(options::syntect_options_set_encoding(
+                    options,
+                    encoding.as_ptr(),
+                    &mut error
+                ));
+                options::syntect_file_set_options(file, options);
+                options::syntect_options_free(options);
+            }
+            let line = syntect_highlight_file_line(file, &mut error);
+            let result = if line.is_null() {
+                Err(unsafe { CStr::from_ptr(error) }
+                    .to_str()
+                    .unwrap()
+                    .to_string())
+            } else {
+                Ok(unsafe { CStr::from_ptr(line) }
+                    .to_str()
+                    .unwrap()
+                    .to_string())
+            };
+            syntect_free_string(line as *mut c_char);
+            syntect_free_string(error as *mut c_char);
+            syntect_free_file(file);
+            result
+        };
+
+        assert!(highlight_first_line(&text_path, None)
+            .unwrap()
+            .contains("caf\u{fffd}"));
+        assert!(highlight_first_line(&text_path, Some("latin1"))
+            .unwrap()
+            .contains("caf\u{e9}"));
+        assert_eq!(
+            highlight_first_line(&binary_path, None).unwrap_err(),
+            "Failed to read file: File appears to be binary"
+        );
+
+        let options = options::syntect_options_new();
+        let encoding = CString::new("no-such-encoding").unwrap();
+        assert!(!options::syntect_options_set_encoding(
+            options,
+            encoding.as_ptr(),
+            &mut error
+        ));
+        syntect_free_string(error as *mut c_char);
+        options::syntect_options_free(options);
+    }
+
     #[test]
     fn test_create_lines() {
         let theme_name = CString::new("base16-ocean.dark").unwrap();
@@ -621,6 +1134,49 @@ mod tests {
         syntect_free_lines(wrapper);
     }
 
+    #[test]
+    fn test_line_endings() {
+        let theme_name = CString::new("base16-ocean.dark").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let highlight = |mode: c_int, line: &str| {
+            let mut error: *const c_char = ptr::null();
+            let lines = syntect_create_lines(theme_name.as_ptr(), &mut error);
+            let options = options::syntect_options_new();
+            assert!(options::syntect_options_set_line_endings(
+                options, mode, &mut error
+            ));
+            options::syntect_lines_set_options(lines, options);
+            options::syntect_options_free(options);
+            let line = CString::new(line).unwrap();
+            let highlighted = syntect_highlight_text_line(lines, line.as_ptr(), &mut error);
+            let result = unsafe { CStr::from_ptr(highlighted) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            syntect_free_string(highlighted as *mut c_char);
+            syntect_free_lines(lines);
+            result
+        };
+
+        let preserved = highlight(0, "fn main() {}\r\n");
+        assert!(preserved.ends_with("m\r\n"), "{:?}", preserved);
+        assert_eq!(preserved.matches('\r').count(), 1);
+        let normalized = highlight(1, "fn main() {}\r\n");
+        assert!(normalized.ends_with("m\n"), "{:?}", normalized);
+        assert!(!normalized.contains('\r'));
+        let stripped = highlight(2, "fn main() {}");
+        assert!(stripped.ends_with('}'), "{:?}", stripped);
+        assert!(stripped.contains("\x1b[38;2;180;142;173mfn"));
+
+        let options = options::syntect_options_new();
+        assert!(!options::syntect_options_set_line_endings(
+            options, 3, &mut error
+        ));
+        syntect_free_string(error as *mut c_char);
+        options::syntect_options_free(options);
+    }
+
     #[test]
     fn test_load_theme_set_from_folder() {
         let folder = CString::new("test/themes").unwrap();
@@ -636,6 +1192,44 @@ mod tests {
         syntect_free_theme_set(theme_set);
     }
 
+    #[test]
+    fn test_load_theme_set_from_folder_name_collision() {
+        let dir = std::env::temp_dir().join("syntect-c-test-theme-collision");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::copy(
+            "test/themes/base16-ocean.tmTheme",
+            dir.join("sub/theme.tmTheme"),
+        )
+        .unwrap();
+        std::fs::copy(
+            "test/themes/sample.sublime-color-scheme",
+            dir.join("theme.sublime-color-scheme"),
+        )
+        .unwrap();
+
+        let theme_set = load_theme_set_from_folder(dir.to_str().unwrap(), false).unwrap();
+        assert_eq!(theme_set.themes.len(), 1);
+        let expected = read_theme(Path::new("test/themes/base16-ocean.tmTheme")).unwrap();
+        assert_eq!(theme_set.themes["theme"], expected);
+    }
+
+    #[test]
+    fn test_load_theme_set_from_folder_with_caching() {
+        theme_cache::use_test_cache_dir();
+        let folder = CString::new("test/themes").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        for _ in 0..2 {
+            let theme_set =
+                syntect_load_theme_set_from_folder_with_caching(folder.as_ptr(), true, &mut error);
+            assert!(!theme_set.is_null(), "Failed to load theme set");
+            let themes = unsafe { &(*theme_set).themes.themes };
+            assert!(themes.contains_key("base16-ocean"));
+            assert!(themes.contains_key("sample"));
+            syntect_free_theme_set(theme_set);
+        }
+    }
+
     #[test]
     fn test_get_theme_names() {
         let folder = CString::new("test/themes").unwrap();
@@ -667,6 +1261,7 @@ mod tests {
 
     #[test]
     fn test_load_theme() {
+        theme_cache::use_test_cache_dir();
         let theme_path = CString::new("test/themes/base16-ocean.tmTheme").unwrap();
         let mut error: *const c_char = ptr::null();
 
@@ -703,6 +1298,7 @@ mod tests {
 
     #[test]
     fn test_get_theme_name() {
+        theme_cache::use_test_cache_dir();
         let theme_path = CString::new("test/themes/base16-ocean.dark").unwrap();
         let mut error: *const c_char = ptr::null();
 
@@ -720,4 +1316,42 @@ mod tests {
         syntect_free_string(theme_name as *mut c_char);
         syntect_free_theme(theme);
     }
+
+    #[test]
+    fn test_load_sublime_color_scheme() {
+        let theme_path = CString::new("test/themes/sample.sublime-color-scheme").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let theme = syntect_load_theme(theme_path.as_ptr(), false, &mut error);
+        assert!(!theme.is_null(), "Failed to load theme");
+        assert!(error.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+
+        let theme_name = syntect_get_theme_name(theme);
+        let theme_name_str = unsafe { CStr::from_ptr(theme_name).to_str().unwrap() };
+        assert_eq!(theme_name_str, "Sample", "Theme name does not match");
+
+        syntect_free_string(theme_name as *mut c_char);
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_get_sublime_color_scheme_from_set() {
+        let folder = CString::new("test/themes").unwrap();
+        let theme_name = CString::new("sample").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let theme_set = syntect_load_theme_set_from_folder(folder.as_ptr(), &mut error);
+        assert!(!theme_set.is_null(), "Failed to load theme set");
+
+        let theme = syntect_get_theme_from_set(theme_set, theme_name.as_ptr(), &mut error);
+        assert!(!theme.is_null(), "Failed to get theme from set");
+        assert!(error.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+
+        syntect_free_theme(theme);
+        syntect_free_theme_set(theme_set);
+    }
 }
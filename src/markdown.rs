@@ -0,0 +1,445 @@
+//! Highlighting of the code blocks in Markdown documents.
+
+use crate::buffer::{bytes_arg, highlight_document_buffer};
+use crate::options::SyntectOptions;
+use crate::render::LineOutput;
+use crate::{highlight_line, initialize, syntax_for, SYNTAX_SET};
+use libc::c_char;
+use std::ffi::CString;
+use std::ops::Range;
+use std::ptr;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::Theme;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// A fenced or indented code block of a Markdown document.
+#[repr(C)]
+pub struct SyntectCodeBlock {
+    /// Byte offset of the start of the block, fences included.
+    pub start: usize,
+    /// Byte offset of the end of the block, fences included.
+    pub end: usize,
+    /// Byte offset of the start of the code.
+    pub code_start: usize,
+    /// Byte offset of the end of the code.
+    pub code_end: usize,
+    /// The language named by the info string of a fenced block, or NULL.
+    pub language: *mut c_char,
+    /// The name of the syntax the block is highlighted with.
+    pub syntax_name: *mut c_char,
+}
+
+/// A code block found by `find_code_blocks`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CodeBlock<'a> {
+    /// The whole block, fences included.
+    pub(crate) range: Range<usize>,
+    /// The code inside the block.
+    pub(crate) code: Range<usize>,
+    /// The first word of a fenced block's info string.
+    pub(crate) language: Option<&'a str>,
+    /// Columns of indentation removed from each line of the code.
+    pub(crate) indent: usize,
+}
+
+struct Fence<'a> {
+    indent: usize,
+    marker: char,
+    length: usize,
+    info: &'a str,
+}
+
+/// Columns of leading whitespace, with tabs advancing to the next multiple of four.
+fn indentation(line: &str) -> usize {
+    let mut columns = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => columns += 1,
+            '\t' => columns += 4 - columns % 4,
+            _ => break,
+        }
+    }
+    columns
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start_matches(' ');
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 || digits > 9 {
+                return false;
+            }
+            match line[digits..].strip_prefix(['.', ')']) {
+                Some(rest) => rest,
+                None => return false,
+            }
+        }
+    };
+    rest.is_empty() || rest.starts_with([' ', '\t', '\n', '\r'])
+}
+
+fn parse_fence(line: &str) -> Option<Fence<'_>> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = rest.len() - rest.trim_start_matches(marker).len();
+    if length < 3 {
+        return None;
+    }
+    let info = rest[length..].trim();
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some(Fence {
+        indent,
+        marker,
+        length,
+        info,
+    })
+}
+
+/// The language named by an info string, such as `rust` in `rust,ignore` or `{.rust}`.
+fn info_language(info: &str) -> Option<&str> {
+    let word = info.split_whitespace().next()?;
+    let word = word.trim_start_matches('{').trim_start_matches('.');
+    let word = word.split([',', '}']).next().unwrap_or("");
+    (!word.is_empty()).then_some(word)
+}
+
+/// Removes up to `columns` columns of indentation from a line.
+fn dedent(line: &str, columns: usize) -> &str {
+    let mut removed = 0;
+    for (index, c) in line.char_indices() {
+        let width = match c {
+            ' ' => 1,
+            '\t' => 4 - removed % 4,
+            _ => return &line[index..],
+        };
+        if removed + width > columns {
+            return &line[index..];
+        }
+        removed += width;
+    }
+    &line[line.len()..]
+}
+
+/// Finds the fenced and indented code blocks of a Markdown document.
+///
+/// This follows CommonMark closely enough for documentation, with one simplification:
+/// indented code is not recognized inside list items, where the indentation usually belongs to
+/// the list.
+pub(crate) fn find_code_blocks(text: &str) -> Vec<CodeBlock<'_>> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in LinesWithEndings::from(text) {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+    let line_end = |index: usize| lines[index].0 + lines[index].1.len();
+
+    let mut blocks = Vec::new();
+    let mut after_blank = true;
+    let mut in_list = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let (start, line) = lines[i];
+
+        if let Some(fence) = parse_fence(line) {
+            let code_start = start + line.len();
+            let close = (i + 1..lines.len()).find(|&j| {
+                parse_fence(lines[j].1).is_some_and(|close| {
+                    close.marker == fence.marker
+                        && close.length >= fence.length
+                        && close.info.is_empty()
+                })
+            });
+            let (code_end, end) = match close {
+                Some(j) => (lines[j].0, line_end(j)),
+                None => (text.len(), text.len()),
+            };
+            blocks.push(CodeBlock {
+                range: start..end,
+                code: code_start..code_end,
+                language: info_language(fence.info),
+                indent: fence.indent,
+            });
+            i = close.map_or(lines.len(), |j| j + 1);
+            after_blank = true;
+            in_list = false;
+            continue;
+        }
+
+        if after_blank && !in_list && !is_blank(line) && indentation(line) >= 4 {
+            let mut last = i;
+            let mut j = i;
+            while j < lines.len() && (is_blank(lines[j].1) || indentation(lines[j].1) >= 4) {
+                if !is_blank(lines[j].1) {
+                    last = j;
+                }
+                j += 1;
+            }
+            blocks.push(CodeBlock {
+                range: start..line_end(last),
+                code: start..line_end(last),
+                language: None,
+                indent: 4,
+            });
+            i = last + 1;
+            after_blank = false;
+            continue;
+        }
+
+        let blank = is_blank(line);
+        if !blank {
+            if is_list_item(line) {
+                in_list = true;
+            } else if after_blank && indentation(line) == 0 {
+                in_list = false;
+            }
+        }
+        after_blank = blank;
+        i += 1;
+    }
+    blocks
+}
+
+/// Resolves the language of a code block to a syntax, falling back to plain text.
+fn block_syntax<'a>(ss: &'a SyntaxSet, block: &CodeBlock) -> &'a SyntaxReference {
+    block
+        .language
+        .and_then(|language| ss.find_syntax_by_token(language))
+        .unwrap_or_else(|| ss.find_syntax_plain_text())
+}
+
+/// Returns the document with each code block replaced by its highlighted code, framed by the
+/// output format's document prefix and suffix.
+pub(crate) fn highlight_markdown(
+    text: &str,
+    theme: &'static Theme,
+    options: &SyntectOptions,
+) -> Result<String, String> {
+    let ss = SYNTAX_SET.get().unwrap();
+    let mut out = String::new();
+    let mut copied = 0;
+    for block in find_code_blocks(text) {
+        out.push_str(&text[copied..block.range.start]);
+        copied = block.range.end;
+
+        let syntax = syntax_for(block_syntax(ss, &block), options.line_endings);
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut output = LineOutput::new(theme, options);
+        output.set_whole_document(true);
+        let mut highlighted = output.begin_document();
+        for line in LinesWithEndings::from(&text[block.code]) {
+            highlighted.push_str(&highlight_line(
+                &mut highlighter,
+                &mut output,
+                options.line_endings,
+                dedent(line, block.indent),
+            )?);
+        }
+        output.close_document(&mut highlighted);
+        out.push_str(&highlighted);
+    }
+    out.push_str(&text[copied..]);
+    Ok(out)
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_markdown_find_code_blocks(
+    data: *const c_char,
+    len: usize,
+    count: *mut usize,
+    error: *mut *const c_char,
+) -> *mut SyntectCodeBlock {
+    assert!(!count.is_null());
+    initialize();
+
+    let Some(text) = bytes_arg(data, len, "Invalid Markdown text", error) else {
+        return ptr::null_mut();
+    };
+    let ss = SYNTAX_SET.get().unwrap();
+
+    let blocks: Box<[SyntectCodeBlock]> = find_code_blocks(text)
+        .into_iter()
+        .map(|block| SyntectCodeBlock {
+            start: block.range.start,
+            end: block.range.end,
+            code_start: block.code.start,
+            code_end: block.code.end,
+            // A language containing NUL cannot be handed to C, and names no syntax anyway.
+            language: block
+                .language
+                .and_then(|language| CString::new(language).ok())
+                .map_or(ptr::null_mut(), CString::into_raw),
+            syntax_name: CString::new(block_syntax(ss, &block).name.replace('\0', ""))
+                .unwrap()
+                .into_raw(),
+        })
+        .collect();
+
+    unsafe {
+        *count = blocks.len();
+    }
+    if blocks.is_empty() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(blocks) as *mut SyntectCodeBlock
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_free_code_blocks(blocks: *mut SyntectCodeBlock, count: usize) {
+    if !blocks.is_null() {
+        unsafe {
+            let blocks = Box::from_raw(ptr::slice_from_raw_parts_mut(blocks, count));
+            for block in blocks.iter() {
+                if !block.language.is_null() {
+                    drop(CString::from_raw(block.language));
+                }
+                drop(CString::from_raw(block.syntax_name));
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_markdown_highlight(
+    data: *const c_char,
+    len: usize,
+    theme_name: *const c_char,
+    options: *const SyntectOptions,
+    out_len: *mut usize,
+    error: *mut *const c_char,
+) -> *const c_char {
+    highlight_document_buffer(
+        data,
+        len,
+        theme_name,
+        options,
+        out_len,
+        error,
+        "Invalid Markdown text",
+        highlight_markdown,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_syntax_and_theme;
+    use crate::options::{LineEndings, OutputFormat};
+
+    const DOCUMENT: &str = "# Title\n\
+        \n\
+        ```rust,ignore\n\
+        fn main() {}\n\
+        ```\n\
+        \n\
+        - item\n\
+        \n\
+        \x20   continued item\n\
+        \n\
+        Paragraph:\n\
+        \n\
+        \x20   indented code\n\
+        \n\
+        \x20 ~~~~ {.python}\n\
+        \x20 x = 1\n\
+        ~~~~\n";
+
+    #[test]
+    fn test_find_code_blocks() {
+        let blocks = find_code_blocks(DOCUMENT);
+        assert_eq!(blocks.len(), 3);
+
+        assert_eq!(
+            &DOCUMENT[blocks[0].range.clone()],
+            "```rust,ignore\nfn main() {}\n```\n"
+        );
+        assert_eq!(&DOCUMENT[blocks[0].code.clone()], "fn main() {}\n");
+        assert_eq!(blocks[0].language, Some("rust"));
+
+        assert_eq!(&DOCUMENT[blocks[1].code.clone()], "    indented code\n");
+        assert_eq!(blocks[1].language, None);
+
+        assert_eq!(&DOCUMENT[blocks[2].code.clone()], "  x = 1\n");
+        assert_eq!(blocks[2].language, Some("python"));
+        assert_eq!(blocks[2].indent, 2);
+        assert_eq!(blocks[2].range.end, DOCUMENT.len());
+
+        // An unclosed fence runs to the end of the document.
+        let blocks = find_code_blocks("text\n```\ncode");
+        assert_eq!(blocks[0].code, 9..13);
+    }
+
+    #[test]
+    fn test_highlight_markdown() {
+        initialize();
+        let (_, theme, _) = get_syntax_and_theme("base16-ocean.dark").unwrap();
+        let options = SyntectOptions {
+            format: OutputFormat::Html,
+            ..SyntectOptions::default()
+        };
+
+        let html = highlight_markdown(DOCUMENT, theme, &options).unwrap();
+        assert!(html.starts_with("# Title\n\n<pre style="));
+        assert_eq!(html.matches("<pre ").count(), 3);
+        assert!(html.contains("</pre>\n\n- item\n"));
+        assert!(html.contains(">indented code</span>"));
+        assert!(html.ends_with("</pre>\n"));
+
+        // Stripped line endings leave the rows of the code blocks apart.
+        let strip = SyntectOptions {
+            line_endings: LineEndings::Strip,
+            ..options
+        };
+        let stripped = highlight_markdown(DOCUMENT, theme, &strip).unwrap();
+        assert_eq!(stripped.lines().count(), html.lines().count());
+        assert!(stripped.contains(">indented code</span>"));
+    }
+
+    #[test]
+    fn test_find_code_blocks_ffi() {
+        let mut count = 0;
+        let mut error: *const c_char = ptr::null();
+        let blocks = syntect_markdown_find_code_blocks(
+            DOCUMENT.as_ptr() as *const c_char,
+            DOCUMENT.len(),
+            &mut count,
+            &mut error,
+        );
+        assert_eq!(count, 3);
+        let blocks_slice = unsafe { std::slice::from_raw_parts(blocks, count) };
+        let name = |s: *mut c_char| unsafe { std::ffi::CStr::from_ptr(s) }.to_str().unwrap();
+        assert_eq!(name(blocks_slice[0].language), "rust");
+        assert_eq!(name(blocks_slice[0].syntax_name), "Rust");
+        assert!(blocks_slice[1].language.is_null());
+        assert_eq!(name(blocks_slice[1].syntax_name), "Plain Text");
+        assert_eq!(name(blocks_slice[2].syntax_name), "Python");
+        syntect_free_code_blocks(blocks, count);
+
+        // Info strings may contain NUL, which C strings cannot.
+        let document = "```ru\0st\nfn main() {}\n```\n";
+        let blocks = syntect_markdown_find_code_blocks(
+            document.as_ptr() as *const c_char,
+            document.len(),
+            &mut count,
+            &mut error,
+        );
+        assert_eq!(count, 1);
+        let block = unsafe { &*blocks };
+        assert!(block.language.is_null());
+        assert_eq!(name(block.syntax_name), "Plain Text");
+        syntect_free_code_blocks(blocks, count);
+    }
+}
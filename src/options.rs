@@ -0,0 +1,507 @@
+//! Output options shared by the file and line highlighters.
+
+use crate::color::parse_css_color;
+use crate::encoding::encoding_for_label;
+use crate::render::{SvgLayout, SyntectRendererCallbacks};
+use crate::{opt_str_arg, set_error, SyntectFile, SyntectLines};
+use encoding_rs::Encoding;
+use libc::{c_char, c_int, c_uint};
+use std::collections::HashMap;
+use syntect::highlighting::{Color, FontStyle};
+
+/// The markup a highlighter renders its output in.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum OutputFormat {
+    Ansi,
+    Html,
+    StyledAnsi,
+    Latex,
+    Rtf,
+    Svg,
+    Pango,
+    Bbcode,
+    /// Rendered by callbacks from C.
+    Callbacks(SyntectRendererCallbacks),
+}
+
+impl OutputFormat {
+    /// Converts a `SyntectOutputFormat` value received from C.
+    fn from_raw(format: c_int) -> Option<OutputFormat> {
+        match format {
+            0 => Some(OutputFormat::Ansi),
+            1 => Some(OutputFormat::Html),
+            2 => Some(OutputFormat::StyledAnsi),
+            3 => Some(OutputFormat::Latex),
+            4 => Some(OutputFormat::Rtf),
+            5 => Some(OutputFormat::Svg),
+            6 => Some(OutputFormat::Pango),
+            7 => Some(OutputFormat::Bbcode),
+            _ => None,
+        }
+    }
+}
+
+/// How control characters in the highlighted text are written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ControlChars {
+    Keep,
+    Caret,
+    Pictures,
+}
+
+impl ControlChars {
+    /// Converts a `SyntectControlChars` value received from C.
+    fn from_raw(mode: c_int) -> Option<ControlChars> {
+        match mode {
+            0 => Some(ControlChars::Keep),
+            1 => Some(ControlChars::Caret),
+            2 => Some(ControlChars::Pictures),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to the line endings of highlighted lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LineEndings {
+    /// Each line is written out with the ending it came with.
+    Preserve,
+    /// `\r\n` endings are written out as `\n`.
+    Normalize,
+    /// Lines come and go without endings, and are highlighted with the syntax set made for
+    /// lines without newlines.
+    Strip,
+}
+
+impl LineEndings {
+    /// Converts a `SyntectLineEndings` value received from C.
+    fn from_raw(mode: c_int) -> Option<LineEndings> {
+        match mode {
+            0 => Some(LineEndings::Preserve),
+            1 => Some(LineEndings::Normalize),
+            2 => Some(LineEndings::Strip),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn strips(self) -> bool {
+        self == LineEndings::Strip
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct SyntectOptions {
+    pub(crate) format: OutputFormat,
+    pub(crate) line_numbers: bool,
+    pub(crate) line_number_width: usize,
+    pub(crate) first_line_number: usize,
+    pub(crate) gutter_separator: Option<String>,
+    /// The line drawn with the theme's line highlight, or 0 for none.
+    pub(crate) highlighted_line: usize,
+    /// Tab stop distance for tab expansion, or 0 to leave tabs alone.
+    pub(crate) tab_width: usize,
+    pub(crate) control_chars: ControlChars,
+    /// Display width to wrap lines at, or 0 to leave them unwrapped.
+    pub(crate) wrap_width: usize,
+    /// Encoding files are read in, or `None` to detect UTF-8 and UTF-16 by byte order mark.
+    pub(crate) encoding: Option<&'static Encoding>,
+    pub(crate) line_endings: LineEndings,
+    /// Backgrounds of added and removed diff lines, or `None` to take them from the theme.
+    pub(crate) diff_added_background: Option<Color>,
+    pub(crate) diff_removed_background: Option<Color>,
+    /// Colors of search matches, or `None` for both to invert the syntax colors.
+    pub(crate) match_foreground: Option<Color>,
+    pub(crate) match_background: Option<Color>,
+    /// Font styles left out of styled terminal output.
+    pub(crate) suppressed_font_styles: FontStyle,
+    pub(crate) svg_layout: SvgLayout,
+}
+
+impl Default for SyntectOptions {
+    fn default() -> Self {
+        SyntectOptions {
+            format: OutputFormat::Ansi,
+            line_numbers: false,
+            line_number_width: 4,
+            first_line_number: 1,
+            gutter_separator: Some("│".to_string()),
+            highlighted_line: 0,
+            tab_width: 0,
+            control_chars: ControlChars::Keep,
+            wrap_width: 0,
+            encoding: None,
+            line_endings: LineEndings::Preserve,
+            diff_added_background: None,
+            diff_removed_background: None,
+            match_foreground: None,
+            match_background: None,
+            suppressed_font_styles: FontStyle::empty(),
+            svg_layout: SvgLayout::default(),
+        }
+    }
+}
+
+/// Converts an optional CSS color argument. NULL maps to `Ok(None)`.
+fn color_arg(color: *const c_char, error: *mut *const c_char) -> Result<Option<Color>, ()> {
+    let Some(color) = opt_str_arg(color, "Invalid color", error)? else {
+        return Ok(None);
+    };
+    parse_css_color(color, &HashMap::new())
+        .map(Some)
+        .map_err(|err| set_error(error, err))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_new() -> *mut SyntectOptions {
+    Box::into_raw(Box::default())
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_free(options: *mut SyntectOptions) {
+    if !options.is_null() {
+        unsafe {
+            drop(Box::from_raw(options));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_format(
+    options: *mut SyntectOptions,
+    format: c_int,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    match OutputFormat::from_raw(format) {
+        Some(format) => {
+            options.format = format;
+            true
+        }
+        None => {
+            set_error(error, format!("Unknown output format {}", format));
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_renderer(
+    options: *mut SyntectOptions,
+    callbacks: *const SyntectRendererCallbacks,
+) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.format = if callbacks.is_null() {
+        OutputFormat::Ansi
+    } else {
+        OutputFormat::Callbacks(unsafe { *callbacks })
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_suppressed_font_styles(
+    options: *mut SyntectOptions,
+    styles: c_uint,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    match u8::try_from(styles).ok().and_then(FontStyle::from_bits) {
+        Some(styles) => {
+            options.suppressed_font_styles = styles;
+            true
+        }
+        None => {
+            set_error(error, format!("Unknown font style flags {}", styles));
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_svg_layout(
+    options: *mut SyntectOptions,
+    font_size: f64,
+    line_height: f64,
+    padding: f64,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+    let valid = font_size.is_finite()
+        && font_size > 0.0
+        && line_height.is_finite()
+        && line_height > 0.0
+        && padding.is_finite()
+        && padding >= 0.0;
+    if !valid {
+        set_error(
+            error,
+            format!(
+                "Invalid SVG layout: font size {}, line height {}, padding {}",
+                font_size, line_height, padding
+            ),
+        );
+        return false;
+    }
+
+    options.svg_layout = SvgLayout {
+        font_size,
+        line_height,
+        padding,
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_line_numbers(options: *mut SyntectOptions, enabled: bool) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.line_numbers = enabled;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_line_number_width(
+    options: *mut SyntectOptions,
+    width: usize,
+) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.line_number_width = width;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_first_line_number(
+    options: *mut SyntectOptions,
+    line_number: usize,
+) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.first_line_number = line_number;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_gutter_separator(
+    options: *mut SyntectOptions,
+    separator: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+    let Ok(separator) = opt_str_arg(separator, "Invalid gutter separator", error) else {
+        return false;
+    };
+
+    options.gutter_separator = separator.map(|separator| separator.to_string());
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_highlighted_line(
+    options: *mut SyntectOptions,
+    line_number: usize,
+) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.highlighted_line = line_number;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_tab_width(options: *mut SyntectOptions, tab_width: usize) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.tab_width = tab_width;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_control_chars(
+    options: *mut SyntectOptions,
+    mode: c_int,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    match ControlChars::from_raw(mode) {
+        Some(mode) => {
+            options.control_chars = mode;
+            true
+        }
+        None => {
+            set_error(error, format!("Unknown control character mode {}", mode));
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_wrap_width(options: *mut SyntectOptions, width: usize) {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    options.wrap_width = width;
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_encoding(
+    options: *mut SyntectOptions,
+    encoding: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+    let Ok(label) = opt_str_arg(encoding, "Invalid encoding", error) else {
+        return false;
+    };
+
+    options.encoding = match label {
+        Some(label) => match encoding_for_label(label) {
+            Some(encoding) => Some(encoding),
+            None => {
+                set_error(error, format!("Unknown encoding '{}'", label));
+                return false;
+            }
+        },
+        None => None,
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_line_endings(
+    options: *mut SyntectOptions,
+    mode: c_int,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+
+    match LineEndings::from_raw(mode) {
+        Some(mode) => {
+            options.line_endings = mode;
+            true
+        }
+        None => {
+            set_error(error, format!("Unknown line ending mode {}", mode));
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_diff_backgrounds(
+    options: *mut SyntectOptions,
+    added: *const c_char,
+    removed: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+    let Ok(added) = color_arg(added, error) else {
+        return false;
+    };
+    let Ok(removed) = color_arg(removed, error) else {
+        return false;
+    };
+
+    options.diff_added_background = added;
+    options.diff_removed_background = removed;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_options_set_match_colors(
+    options: *mut SyntectOptions,
+    foreground: *const c_char,
+    background: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let options = unsafe {
+        assert!(!options.is_null());
+        &mut *options
+    };
+    let Ok(foreground) = color_arg(foreground, error) else {
+        return false;
+    };
+    let Ok(background) = color_arg(background, error) else {
+        return false;
+    };
+
+    options.match_foreground = foreground;
+    options.match_background = background;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_file_set_options(
+    wrapper: *mut SyntectFile,
+    options: *const SyntectOptions,
+) {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let options = unsafe {
+        assert!(!options.is_null());
+        &*options
+    };
+
+    wrapper.set_options(options);
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_lines_set_options(
+    wrapper: *mut SyntectLines,
+    options: *const SyntectOptions,
+) {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let options = unsafe {
+        assert!(!options.is_null());
+        &*options
+    };
+
+    wrapper.set_options(options);
+}
@@ -0,0 +1,123 @@
+use super::{blend, Renderer};
+use std::fmt::Write;
+use syntect::highlighting::{FontStyle, Style};
+
+/// 24-bit color terminal output, matching syntect's `as_24_bit_terminal_escaped`.
+pub(crate) struct AnsiRenderer;
+
+fn write_colors(out: &mut String, style: Style) {
+    let bg = style.background;
+    let fg = blend(style.foreground, bg);
+    let _ = write!(
+        out,
+        "\x1b[48;2;{};{};{}m\x1b[38;2;{};{};{}m",
+        bg.r, bg.g, bg.b, fg.r, fg.g, fg.b
+    );
+}
+
+impl Renderer for AnsiRenderer {
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        write_colors(out, style);
+        out.push_str(text);
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("\x1b[0m");
+    }
+}
+
+/// The SGR codes that turn each font style on and off.
+const SGR_CODES: [(FontStyle, u8, u8); 3] = [
+    (FontStyle::BOLD, 1, 22),
+    (FontStyle::ITALIC, 3, 23),
+    (FontStyle::UNDERLINE, 4, 24),
+];
+
+/// 24-bit color terminal output with the theme's bold, italic and underline styles.
+///
+/// Font styles are only written when they change, and are turned off at the end of each row
+/// so that a line can be printed on its own.
+pub(crate) struct StyledAnsiRenderer {
+    /// The font styles that are written out.
+    font_styles: FontStyle,
+    /// The font styles in effect in the terminal.
+    current: FontStyle,
+}
+
+impl StyledAnsiRenderer {
+    pub(crate) fn new(font_styles: FontStyle) -> StyledAnsiRenderer {
+        StyledAnsiRenderer {
+            font_styles,
+            current: FontStyle::empty(),
+        }
+    }
+
+    fn set_font_style(&mut self, out: &mut String, font_style: FontStyle) {
+        if font_style == self.current {
+            return;
+        }
+        let codes: Vec<String> = SGR_CODES
+            .iter()
+            .filter(|(style, _, _)| font_style.contains(*style) != self.current.contains(*style))
+            .map(|&(style, on, off)| if font_style.contains(style) { on } else { off }.to_string())
+            .collect();
+        let _ = write!(out, "\x1b[{}m", codes.join(";"));
+        self.current = font_style;
+    }
+}
+
+impl Renderer for StyledAnsiRenderer {
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        write_colors(out, style);
+        self.set_font_style(out, style.font_style & self.font_styles);
+        out.push_str(text);
+    }
+
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        self.set_font_style(out, FontStyle::empty());
+        out.push_str(line_ending);
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("\x1b[0m");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::Color;
+
+    fn style(font_style: FontStyle) -> Style {
+        Style {
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            font_style,
+        }
+    }
+
+    #[test]
+    fn test_styled_ansi() {
+        let mut renderer = StyledAnsiRenderer::new(FontStyle::all());
+        let mut out = String::new();
+        renderer.token(&mut out, style(FontStyle::BOLD | FontStyle::ITALIC), "a");
+        renderer.token(&mut out, style(FontStyle::BOLD), "b");
+        renderer.token(&mut out, style(FontStyle::BOLD), "c");
+        renderer.end_line(&mut out, "\n");
+        let colors = "\x1b[48;2;0;0;0m\x1b[38;2;255;255;255m";
+        assert_eq!(
+            out,
+            format!("{colors}\x1b[1;3ma{colors}\x1b[23mb{colors}c\x1b[22m\n")
+        );
+
+        let mut renderer = StyledAnsiRenderer::new(FontStyle::BOLD);
+        let mut out = String::new();
+        renderer.token(
+            &mut out,
+            style(FontStyle::ITALIC | FontStyle::UNDERLINE),
+            "a",
+        );
+        renderer.end_line(&mut out, "");
+        assert_eq!(out, format!("{colors}a"));
+    }
+}
@@ -0,0 +1,72 @@
+use super::{blend, Renderer};
+use std::fmt::Write;
+use syntect::highlighting::{FontStyle, Style};
+
+/// BBCode for forum posts, with `[color]`, `[b]`, `[i]` and `[u]` tags. BBCode has no
+/// backgrounds, so only foregrounds are kept.
+pub(crate) struct BbcodeRenderer;
+
+/// BBCode has no escape of its own, so brackets are written as HTML character references,
+/// which forums decode in posts, and ampersands are escaped to keep the text as written.
+fn escape_bbcode(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '[' => out.push_str("&#91;"),
+            ']' => out.push_str("&#93;"),
+            '&' => out.push_str("&amp;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+impl Renderer for BbcodeRenderer {
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let foreground = blend(style.foreground, style.background);
+        let _ = write!(
+            out,
+            "[color=#{:02x}{:02x}{:02x}]",
+            foreground.r, foreground.g, foreground.b
+        );
+        let tags: Vec<&str> = [
+            (FontStyle::BOLD, "b"),
+            (FontStyle::ITALIC, "i"),
+            (FontStyle::UNDERLINE, "u"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| style.font_style.contains(*flag))
+        .map(|(_, tag)| tag)
+        .collect();
+        for tag in &tags {
+            let _ = write!(out, "[{}]", tag);
+        }
+        escape_bbcode(out, text);
+        for tag in tags.iter().rev() {
+            let _ = write!(out, "[/{}]", tag);
+        }
+        out.push_str("[/color]");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::Color;
+
+    #[test]
+    fn test_bbcode() {
+        let style = Style {
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        };
+        let mut out = String::new();
+        BbcodeRenderer.token(&mut out, style, "a[i] <&>");
+        assert_eq!(
+            out,
+            "[color=#ffffff][b][i]a&#91;i&#93; <&amp;>[/i][/b][/color]"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use super::{blend, Renderer};
+use crate::spans::SyntectColor;
+use libc::{c_char, c_void};
+use syntect::highlighting::Style;
+
+/// The output of a document being rendered, handed to the callbacks of a C renderer.
+#[repr(transparent)]
+pub struct SyntectOutput(String);
+
+/// The style of a token handed to a C renderer.
+#[repr(C)]
+pub struct SyntectTokenStyle {
+    /// The foreground, drawn over the background so that it is opaque.
+    pub foreground: SyntectColor,
+    pub background: SyntectColor,
+    /// A combination of `SyntectFontStyle` flags.
+    pub font_style: u8,
+}
+
+/// A renderer implemented in C. Every callback may be NULL, and gets `user_data` back.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SyntectRendererCallbacks {
+    pub user_data: *mut c_void,
+    pub begin_document: Option<extern "C" fn(*mut c_void, *mut SyntectOutput)>,
+    pub begin_line: Option<extern "C" fn(*mut c_void, *mut SyntectOutput, usize)>,
+    pub token: Option<
+        extern "C" fn(
+            *mut c_void,
+            *mut SyntectOutput,
+            *const SyntectTokenStyle,
+            *const c_char,
+            usize,
+        ),
+    >,
+    pub end_line: Option<extern "C" fn(*mut c_void, *mut SyntectOutput, *const c_char, usize)>,
+    pub end_document: Option<extern "C" fn(*mut c_void, *mut SyntectOutput)>,
+}
+
+/// Drives the callbacks of a C renderer, which write their output with `syntect_output_write`.
+pub(crate) struct CallbackRenderer {
+    callbacks: SyntectRendererCallbacks,
+}
+
+impl CallbackRenderer {
+    pub(crate) fn new(callbacks: SyntectRendererCallbacks) -> CallbackRenderer {
+        CallbackRenderer { callbacks }
+    }
+}
+
+/// Lends `out` to a callback as a `SyntectOutput`.
+fn output(out: &mut String) -> *mut SyntectOutput {
+    out as *mut String as *mut SyntectOutput
+}
+
+impl Renderer for CallbackRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        if let Some(begin_document) = self.callbacks.begin_document {
+            begin_document(self.callbacks.user_data, output(out));
+        }
+    }
+
+    fn begin_line(&mut self, out: &mut String, line_number: usize) {
+        if let Some(begin_line) = self.callbacks.begin_line {
+            begin_line(self.callbacks.user_data, output(out), line_number);
+        }
+    }
+
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if let Some(token) = self.callbacks.token {
+            let style = SyntectTokenStyle {
+                foreground: blend(style.foreground, style.background).into(),
+                background: style.background.into(),
+                font_style: style.font_style.bits(),
+            };
+            token(
+                self.callbacks.user_data,
+                output(out),
+                &style,
+                text.as_ptr().cast(),
+                text.len(),
+            );
+        }
+    }
+
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        if let Some(end_line) = self.callbacks.end_line {
+            end_line(
+                self.callbacks.user_data,
+                output(out),
+                line_ending.as_ptr().cast(),
+                line_ending.len(),
+            );
+        }
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        if let Some(end_document) = self.callbacks.end_document {
+            end_document(self.callbacks.user_data, output(out));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_output_write(
+    output: *mut SyntectOutput,
+    data: *const c_char,
+    len: usize,
+) -> bool {
+    let output = unsafe {
+        assert!(!output.is_null());
+        &mut *output
+    };
+    assert!(len == 0 || !data.is_null());
+    let bytes = match len {
+        0 => &[][..],
+        _ => unsafe { std::slice::from_raw_parts(data.cast::<u8>(), len) },
+    };
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            output.0.push_str(text);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::{Color, FontStyle};
+
+    extern "C" fn token(
+        user_data: *mut c_void,
+        out: *mut SyntectOutput,
+        style: *const SyntectTokenStyle,
+        text: *const c_char,
+        len: usize,
+    ) {
+        let tokens = unsafe { &mut *(user_data as *mut usize) };
+        *tokens += 1;
+        let style = unsafe { &*style };
+        let bold = if style.font_style & 1 != 0 { "*" } else { "" };
+        assert!(syntect_output_write(out, bold.as_ptr().cast(), bold.len()));
+        assert!(syntect_output_write(out, text, len));
+    }
+
+    extern "C" fn end_line(
+        _user_data: *mut c_void,
+        out: *mut SyntectOutput,
+        line_ending: *const c_char,
+        len: usize,
+    ) {
+        assert!(syntect_output_write(out, "|".as_ptr().cast(), 1));
+        assert!(syntect_output_write(out, line_ending, len));
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let mut tokens = 0usize;
+        let mut renderer = CallbackRenderer::new(SyntectRendererCallbacks {
+            user_data: &mut tokens as *mut usize as *mut c_void,
+            begin_document: None,
+            begin_line: None,
+            token: Some(token),
+            end_line: Some(end_line),
+            end_document: None,
+        });
+        let style = Style {
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            font_style: FontStyle::BOLD,
+        };
+        let mut out = String::new();
+        renderer.begin_document(&mut out);
+        renderer.token(&mut out, style, "fn");
+        renderer.token(&mut out, Style::default(), " main");
+        renderer.end_line(&mut out, "\n");
+        renderer.end_document(&mut out);
+        assert_eq!(out, "*fn main|\n");
+        assert_eq!(tokens, 2);
+
+        let invalid = [0xffu8];
+        assert!(!syntect_output_write(
+            output(&mut out),
+            invalid.as_ptr().cast(),
+            1
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use super::Renderer;
+use std::fmt::Write;
+use syntect::highlighting::{Color, FontStyle, Style};
+
+/// Inline-styled HTML, one `<span>` per token, in a `<pre>` with the theme background.
+pub(crate) struct HtmlRenderer {
+    background: Color,
+}
+
+impl HtmlRenderer {
+    pub(crate) fn new(background: Color) -> HtmlRenderer {
+        HtmlRenderer { background }
+    }
+}
+
+fn css_color(color: Color) -> String {
+    if color.a == 0xff {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    } else {
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.r, color.g, color.b, color.a
+        )
+    }
+}
+
+pub(crate) fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "<pre style=\"background-color:{};\">",
+            css_color(self.background)
+        );
+    }
+
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        out.push_str("<span style=\"");
+        if style.background != self.background {
+            let _ = write!(out, "background-color:{};", css_color(style.background));
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            out.push_str("text-decoration:underline;");
+        }
+        if style.font_style.contains(FontStyle::BOLD) {
+            out.push_str("font-weight:bold;");
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            out.push_str("font-style:italic;");
+        }
+        let _ = write!(out, "color:{};\">", css_color(style.foreground));
+        escape_html(out, text);
+        out.push_str("</span>");
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("</pre>\n");
+    }
+}
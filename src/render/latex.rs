@@ -0,0 +1,167 @@
+use super::{blend, theme_palette, Renderer};
+use std::fmt::Write;
+use syntect::highlighting::{Color, FontStyle, Style, Theme};
+
+/// LaTeX for `\ttfamily` listings, coloring tokens with xcolor's `\textcolor`.
+///
+/// Colors of the theme are referred to by the names that `latex_preamble` defines; other
+/// colors, such as blended highlights, are given inline in HTML notation.
+pub(crate) struct LatexRenderer {
+    background: Color,
+    palette: Vec<Color>,
+}
+
+impl LatexRenderer {
+    pub(crate) fn new(theme: &Theme) -> LatexRenderer {
+        LatexRenderer {
+            background: theme.settings.background.unwrap_or(Color::WHITE),
+            palette: theme_palette(theme),
+        }
+    }
+
+    fn color(&self, color: Color) -> String {
+        if self.palette.contains(&color) {
+            format!("{{{}}}", color_name(color))
+        } else {
+            format!("[HTML]{{{}}}", hex(color))
+        }
+    }
+}
+
+fn hex(color: Color) -> String {
+    format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+fn color_name(color: Color) -> String {
+    format!("syntect{}", hex(color))
+}
+
+/// Returns the preamble lines that load xcolor and define the colors of a theme, named
+/// `syntect` followed by their hex code, with `syntectforeground` and `syntectbackground`
+/// for the default colors.
+pub(crate) fn latex_preamble(theme: &Theme) -> String {
+    let settings = &theme.settings;
+    let background = settings.background.unwrap_or(Color::WHITE);
+    let foreground = blend(settings.foreground.unwrap_or(Color::BLACK), background);
+
+    let mut out = String::from("\\usepackage{xcolor}\n");
+    let mut define = |name: &str, color: Color| {
+        let _ = writeln!(out, "\\definecolor{{{}}}{{HTML}}{{{}}}", name, hex(color));
+    };
+    define("syntectforeground", foreground);
+    define("syntectbackground", background);
+    for color in theme_palette(theme) {
+        define(&color_name(color), color);
+    }
+    out
+}
+
+fn escape_latex(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '\'' => out.push_str("\\textquotesingle{}"),
+            '`' => out.push_str("\\textasciigrave{}"),
+            '"' => out.push_str("\\textquotedbl{}"),
+            '<' => out.push_str("\\textless{}"),
+            '>' => out.push_str("\\textgreater{}"),
+            // Ties keep runs of spaces from collapsing.
+            ' ' | '\t' => out.push('~'),
+            _ => out.push(c),
+        }
+    }
+}
+
+impl Renderer for LatexRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        out.push_str(
+            "\\begingroup\\ttfamily\\raggedright\\setlength{\\parindent}{0pt}\
+             \\setlength{\\parskip}{0pt}\\setlength{\\fboxsep}{0pt}%\n",
+        );
+    }
+
+    fn begin_line(&mut self, out: &mut String, _line_number: usize) {
+        // Keeps empty lines from being dropped.
+        out.push_str("\\mbox{}");
+    }
+
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let mut closing = 1;
+        if style.background != self.background {
+            let _ = write!(out, "\\colorbox{}{{\\strut ", self.color(style.background));
+            closing += 1;
+        }
+        let foreground = blend(style.foreground, style.background);
+        let _ = write!(out, "\\textcolor{}{{", self.color(foreground));
+        for (flag, command) in [
+            (FontStyle::BOLD, "\\textbf{"),
+            (FontStyle::ITALIC, "\\textit{"),
+            (FontStyle::UNDERLINE, "\\underline{"),
+        ] {
+            if style.font_style.contains(flag) {
+                out.push_str(command);
+                closing += 1;
+            }
+        }
+        escape_latex(out, text);
+        out.push_str(&"}".repeat(closing));
+    }
+
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        out.push_str("\\par");
+        out.push_str(line_ending);
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("\\endgroup\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+
+    #[test]
+    fn test_latex() {
+        let ts = ThemeSet::load_defaults();
+        let theme = &ts.themes["base16-ocean.dark"];
+        let preamble = latex_preamble(theme);
+        assert!(preamble.starts_with("\\usepackage{xcolor}\n"));
+        assert!(preamble.contains("\\definecolor{syntectbackground}{HTML}{2B303B}\n"));
+        assert!(preamble.contains("\\definecolor{syntectB48EAD}{HTML}{B48EAD}\n"));
+
+        let mut renderer = LatexRenderer::new(theme);
+        let mut out = String::new();
+        let keyword = Style {
+            foreground: Color {
+                r: 0xb4,
+                g: 0x8e,
+                b: 0xad,
+                a: 0xff,
+            },
+            background: renderer.background,
+            font_style: FontStyle::BOLD,
+        };
+        renderer.token(&mut out, keyword, "a_b {~}");
+        let highlighted = Style {
+            background: Color::BLACK,
+            ..keyword
+        };
+        renderer.token(&mut out, highlighted, "\\");
+        assert_eq!(
+            out,
+            "\\textcolor{syntectB48EAD}{\\textbf{a\\_b~\\{\\textasciitilde{}\\}}}\
+             \\colorbox[HTML]{000000}{\\strut \\textcolor{syntectB48EAD}{\\textbf{\\textbackslash{}}}}"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Rendering of highlighted lines into the supported output formats.
+
+mod ansi;
+mod bbcode;
+mod callbacks;
+mod html;
+mod latex;
+mod pango;
+mod rtf;
+mod svg;
+mod text;
+
+pub(crate) use callbacks::SyntectRendererCallbacks;
+pub(crate) use latex::latex_preamble;
+pub(crate) use svg::SvgLayout;
+
+use crate::color::blend;
+use crate::options::{LineEndings, OutputFormat, SyntectOptions};
+use syntect::highlighting::{Color, FontStyle, Style, Theme};
+
+/// Receives the styled pieces of a document and writes them out in one output format.
+///
+/// The line ending is passed to `end_line` rather than being part of the last token, and
+/// tokens may be empty.
+pub(crate) trait Renderer {
+    fn begin_document(&mut self, _out: &mut String) {}
+    fn begin_line(&mut self, _out: &mut String, _line_number: usize) {}
+    fn token(&mut self, out: &mut String, style: Style, text: &str);
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        out.push_str(line_ending);
+    }
+    fn end_document(&mut self, _out: &mut String) {}
+    /// Called with a whole document, for formats that can only describe it once they have
+    /// seen all of it.
+    fn complete_document(&mut self, _document: &mut String) {}
+}
+
+/// Creates the renderer for the output format. `decorations` are the colors the line output
+/// draws on top of the theme's, for formats that have to declare their colors up front.
+fn new_renderer(
+    options: &SyntectOptions,
+    theme: &Theme,
+    decorations: &[Color],
+) -> Box<dyn Renderer> {
+    let background = theme.settings.background.unwrap_or(Color::WHITE);
+    match options.format {
+        OutputFormat::Ansi => Box::new(ansi::AnsiRenderer),
+        OutputFormat::StyledAnsi => Box::new(ansi::StyledAnsiRenderer::new(
+            FontStyle::all() - options.suppressed_font_styles,
+        )),
+        OutputFormat::Latex => Box::new(latex::LatexRenderer::new(theme)),
+        OutputFormat::Rtf => Box::new(rtf::RtfRenderer::new(theme, options, decorations)),
+        OutputFormat::Svg => Box::new(svg::SvgRenderer::new(options.svg_layout, background)),
+        OutputFormat::Pango => Box::new(pango::PangoRenderer::new(
+            theme.settings.foreground.unwrap_or(Color::BLACK),
+            background,
+        )),
+        OutputFormat::Bbcode => Box::new(bbcode::BbcodeRenderer),
+        OutputFormat::Callbacks(callbacks) => Box::new(callbacks::CallbackRenderer::new(callbacks)),
+        OutputFormat::Html => Box::new(html::HtmlRenderer::new(background)),
+    }
+}
+
+/// The opaque colors of a theme's settings and rules, translucent ones drawn over its
+/// background.
+fn theme_palette(theme: &Theme) -> Vec<Color> {
+    let settings = &theme.settings;
+    let background = settings.background.unwrap_or(Color::WHITE);
+    let colors = [settings.foreground, settings.background]
+        .into_iter()
+        .chain(
+            theme
+                .scopes
+                .iter()
+                .flat_map(|item| [item.style.foreground, item.style.background]),
+        )
+        .flatten();
+
+    let mut palette = Vec::new();
+    for color in colors {
+        let color = blend(color, background);
+        if !palette.contains(&color) {
+            palette.push(color);
+        }
+    }
+    palette
+}
+
+/// Turns the highlighted lines of a file or text into output, adding the decorations asked
+/// for in the options.
+pub(crate) struct LineOutput {
+    options: SyntectOptions,
+    renderer: Box<dyn Renderer>,
+    background: Color,
+    gutter: Style,
+    line_highlight: Option<Color>,
+    line_number: usize,
+    /// Whether the lines make up a whole document, whose rows are separated by `\n` even when
+    /// line endings are stripped.
+    whole_document: bool,
+}
+
+impl LineOutput {
+    pub(crate) fn new(theme: &Theme, options: &SyntectOptions) -> LineOutput {
+        let settings = &theme.settings;
+        let background = settings.background.unwrap_or(Color::WHITE);
+        let gutter_background = blend(settings.gutter.unwrap_or(background), background);
+        // Without a gutter foreground, line numbers are drawn in a dimmed text color.
+        let gutter_foreground = settings.gutter_foreground.unwrap_or(Color {
+            a: 0x80,
+            ..settings.foreground.unwrap_or(Color::BLACK)
+        });
+        let gutter = Style {
+            foreground: blend(gutter_foreground, gutter_background),
+            background: gutter_background,
+            font_style: FontStyle::empty(),
+        };
+        let line_highlight = settings
+            .line_highlight
+            .map(|color| blend(color, background));
+        let decorations: Vec<Color> = [gutter.foreground, gutter.background]
+            .into_iter()
+            .chain(line_highlight)
+            .collect();
+        LineOutput {
+            options: options.clone(),
+            renderer: new_renderer(options, theme, &decorations),
+            background,
+            gutter,
+            line_highlight,
+            line_number: options.first_line_number,
+            whole_document: false,
+        }
+    }
+
+    /// Sets whether the lines rendered from now on make up a whole document, as they do for
+    /// the functions that highlight all of a text at once.
+    pub(crate) fn set_whole_document(&mut self, whole_document: bool) {
+        self.whole_document = whole_document;
+    }
+
+    /// Fails for output formats that cannot be written a line at a time: the root element of
+    /// an SVG image needs its size, which is only known after the last line.
+    pub(crate) fn check_line_by_line(&self) -> Result<(), String> {
+        match self.options.format {
+            OutputFormat::Svg if !self.whole_document => Err(
+                "SVG output needs the whole text at once, as by syntect_highlight_buffer"
+                    .to_string(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns what the output format puts before the first line, such as `<pre>` for HTML.
+    pub(crate) fn begin_document(&mut self) -> String {
+        let mut out = String::new();
+        self.renderer.begin_document(&mut out);
+        out
+    }
+
+    /// Returns what the output format puts after the last line.
+    pub(crate) fn end_document(&mut self) -> String {
+        let mut out = String::new();
+        self.renderer.end_document(&mut out);
+        out
+    }
+
+    /// Appends what the output format puts after the last line to a whole document, and lets
+    /// the format fill in what depends on all of it, such as the size of an SVG image.
+    pub(crate) fn close_document(&mut self, document: &mut String) {
+        self.renderer.end_document(document);
+        self.renderer.complete_document(document);
+    }
+
+    /// Renders one highlighted line. `line_ending` is the ending the line came with, which is
+    /// written out according to the line ending policy; a `\n` left in the regions is dropped.
+    pub(crate) fn render_line(&mut self, regions: &[(Style, &str)], line_ending: &str) -> String {
+        let regions = strip_newline(regions);
+        // Rows of a wrapped line end like the line itself; without an ending to copy, and when
+        // endings are stripped, they end with `\n`.
+        let row_break = match (self.options.line_endings, line_ending) {
+            (LineEndings::Preserve, ending) if !ending.is_empty() => ending,
+            _ => "\n",
+        };
+        let line_ending = match self.options.line_endings {
+            LineEndings::Preserve => line_ending,
+            LineEndings::Normalize if line_ending.is_empty() => "",
+            LineEndings::Normalize => "\n",
+            LineEndings::Strip if self.whole_document && !line_ending.is_empty() => "\n",
+            LineEndings::Strip => "",
+        };
+        let regions =
+            text::make_visible(regions, self.options.tab_width, self.options.control_chars);
+        let line_number = self.line_number;
+        self.line_number += 1;
+
+        let rows = match self.options.wrap_width {
+            0 => vec![regions],
+            width => text::wrap(regions, width),
+        };
+        let line_highlight = self
+            .line_highlight
+            .filter(|_| line_number == self.options.highlighted_line);
+
+        let mut out = String::new();
+        let last_row = rows.len() - 1;
+        for (row_index, row) in rows.into_iter().enumerate() {
+            self.renderer.begin_line(&mut out, line_number);
+            if self.options.line_numbers {
+                // Continuation rows get an empty gutter of the same width.
+                let number = if row_index == 0 {
+                    line_number.to_string()
+                } else {
+                    String::new()
+                };
+                let number = format!(
+                    "{:>width$} ",
+                    number,
+                    width = self
+                        .options
+                        .line_number_width
+                        .max(line_number.to_string().len())
+                );
+                self.renderer.token(&mut out, self.gutter, &number);
+                if let Some(separator) = &self.options.gutter_separator {
+                    self.renderer
+                        .token(&mut out, self.gutter, &format!("{} ", separator));
+                }
+            }
+
+            for (mut style, text) in row {
+                if let Some(line_highlight) = line_highlight {
+                    // Tokens with a background of their own keep it.
+                    if style.background == self.background {
+                        style.background = line_highlight;
+                    }
+                }
+                self.renderer.token(&mut out, style, &text);
+            }
+            let row_ending = if row_index == last_row {
+                line_ending
+            } else {
+                row_break
+            };
+            self.renderer.end_line(&mut out, row_ending);
+        }
+        out
+    }
+}
+
+/// Removes the trailing newline from the last region of a line.
+fn strip_newline<'a>(regions: &[(Style, &'a str)]) -> Vec<(Style, &'a str)> {
+    let mut regions = regions.to_vec();
+    if let Some((_, text)) = regions.last_mut() {
+        *text = text.strip_suffix('\n').unwrap_or(text);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::OutputFormat;
+    use syntect::easy::HighlightLines;
+    use syntect::highlighting::ThemeSet;
+    use syntect::parsing::SyntaxSet;
+    use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+    const CODE: &str = "// <comment>\nfn main() {\n    let x = \"&\";\n}";
+
+    fn render(options: &SyntectOptions) -> Vec<String> {
+        let ss = SyntaxSet::load_defaults_newlines();
+        let ts = ThemeSet::load_defaults();
+        let theme = &ts.themes["base16-ocean.dark"];
+        let syntax = ss.find_syntax_by_extension("rs").unwrap();
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut output = LineOutput::new(theme, options);
+        LinesWithEndings::from(CODE)
+            .map(|line| {
+                let regions = highlighter.highlight_line(line, &ss).unwrap();
+                let line_ending = if line.ends_with('\n') { "\n" } else { "" };
+                output.render_line(&regions, line_ending)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_output_unchanged() {
+        let ss = SyntaxSet::load_defaults_newlines();
+        let ts = ThemeSet::load_defaults();
+        let syntax = ss.find_syntax_by_extension("rs").unwrap();
+        let mut highlighter = HighlightLines::new(syntax, &ts.themes["base16-ocean.dark"]);
+        let expected: Vec<String> = LinesWithEndings::from(CODE)
+            .map(|line| {
+                let regions = highlighter.highlight_line(line, &ss).unwrap();
+                as_24_bit_terminal_escaped(&regions, true)
+            })
+            .collect();
+
+        assert_eq!(render(&SyntectOptions::default()), expected);
+    }
+
+    #[test]
+    fn test_line_numbers_and_highlighted_line() {
+        let options = SyntectOptions {
+            line_numbers: true,
+            first_line_number: 9,
+            highlighted_line: 10,
+            ..SyntectOptions::default()
+        };
+        let lines = render(&options);
+
+        // base16-ocean.dark has no gutter colors, so the gutter is drawn in dimmed text.
+        assert!(
+            lines[0].starts_with("\x1b[48;2;43;48;59m\x1b[38;2;117;122;132m   9 "),
+            "{:?}",
+            lines[0]
+        );
+        assert!(lines[0].contains("│ "));
+        assert!(lines[1].contains("  10 "));
+        // Only line 10 gets the line highlight (#65737E30 drawn over #2B303B).
+        assert!(!lines[0].contains("\x1b[48;2;53;60;71m"));
+        assert!(lines[1].contains("\x1b[48;2;53;60;71m\x1b[38;2;180;142;173mfn"));
+        assert!(lines[3].ends_with('}'));
+    }
+
+    #[test]
+    fn test_wrapped_lines() {
+        let options = SyntectOptions {
+            line_numbers: true,
+            line_number_width: 2,
+            gutter_separator: None,
+            wrap_width: 8,
+            ..SyntectOptions::default()
+        };
+        let lines = render(&options);
+
+        // "// <comment>" wraps after eight columns; the continuation row re-opens the comment
+        // color and has an empty gutter.
+        let rows: Vec<&str> = lines[0].split_inclusive('\n').collect();
+        assert_eq!(rows.len(), 2, "{:?}", lines[0]);
+        assert!(
+            rows[0].ends_with("\x1b[38;2;101;115;126m <comm\n"),
+            "{:?}",
+            rows[0]
+        );
+        assert!(rows[1].starts_with("\x1b[48;2;43;48;59m\x1b[38;2;117;122;132m   "));
+        assert!(
+            rows[1].contains("\x1b[38;2;101;115;126ment>"),
+            "{:?}",
+            rows[1]
+        );
+        assert_eq!(lines[3].matches('\n').count(), 0);
+    }
+
+    #[test]
+    fn test_wrapped_crlf_lines() {
+        let style = Style {
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            font_style: FontStyle::empty(),
+        };
+        let theme = Theme::default();
+        let wrapped = |line_endings: LineEndings| {
+            let options = SyntectOptions {
+                format: OutputFormat::Html,
+                wrap_width: 4,
+                line_endings,
+                ..SyntectOptions::default()
+            };
+            let mut output = LineOutput::new(&theme, &options);
+            output.render_line(&[(style, "abcdefghij\n")], "\r\n")
+        };
+
+        let preserved = wrapped(LineEndings::Preserve);
+        assert_eq!(preserved.matches("\r\n").count(), 3, "{:?}", preserved);
+        assert_eq!(preserved.matches('\n').count(), 3);
+        let normalized = wrapped(LineEndings::Normalize);
+        assert_eq!(normalized.matches('\n').count(), 3);
+        assert!(!normalized.contains('\r'));
+        let stripped = wrapped(LineEndings::Strip);
+        assert_eq!(stripped.matches('\n').count(), 2);
+        assert!(!stripped.contains('\r') && stripped.ends_with("</span>"));
+    }
+
+    #[test]
+    fn test_html_output() {
+        let options = SyntectOptions {
+            format: OutputFormat::Html,
+            line_numbers: true,
+            gutter_separator: None,
+            ..SyntectOptions::default()
+        };
+        let lines = render(&options);
+
+        assert_eq!(
+            lines[0],
+            "<span style=\"color:#757a84;\">   1 </span>\
+             <span style=\"color:#65737e;\">//</span>\
+             <span style=\"color:#65737e;\"> &lt;comment&gt;</span>\n"
+        );
+        assert!(lines[2].contains("&quot;</span><span style=\"color:#a3be8c;\">&amp;</span>"));
+    }
+}
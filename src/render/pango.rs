@@ -0,0 +1,94 @@
+use super::html::escape_html;
+use super::text::xml_safe;
+use super::{blend, Renderer};
+use std::fmt::Write;
+use syntect::highlighting::{Color, FontStyle, Style};
+
+/// Pango markup for GTK labels and text views, one `<span>` per token inside a monospace span
+/// with the theme colors.
+pub(crate) struct PangoRenderer {
+    foreground: Color,
+    background: Color,
+}
+
+impl PangoRenderer {
+    pub(crate) fn new(foreground: Color, background: Color) -> PangoRenderer {
+        PangoRenderer {
+            foreground,
+            background,
+        }
+    }
+}
+
+fn color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+impl Renderer for PangoRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        let _ = write!(
+            out,
+            "<span font_family=\"monospace\" foreground=\"{}\" background=\"{}\">",
+            color(blend(self.foreground, self.background)),
+            color(self.background)
+        );
+    }
+
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let foreground = blend(style.foreground, style.background);
+        let _ = write!(out, "<span foreground=\"{}\"", color(foreground));
+        if style.background != self.background {
+            let _ = write!(out, " background=\"{}\"", color(style.background));
+        }
+        if style.font_style.contains(FontStyle::BOLD) {
+            out.push_str(" weight=\"bold\"");
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            out.push_str(" style=\"italic\"");
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            out.push_str(" underline=\"single\"");
+        }
+        out.push('>');
+        // GMarkup rejects control characters that XML does not allow.
+        escape_html(out, &xml_safe(text));
+        out.push_str("</span>");
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("</span>");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pango() {
+        let mut renderer = PangoRenderer::new(Color::WHITE, Color::BLACK);
+        let mut out = String::new();
+        renderer.begin_document(&mut out);
+        let style = Style {
+            foreground: Color::WHITE,
+            background: Color::WHITE,
+            font_style: FontStyle::ITALIC | FontStyle::UNDERLINE,
+        };
+        renderer.token(&mut out, style, "a<&'b'>");
+        renderer.end_line(&mut out, "\n");
+        renderer.end_document(&mut out);
+        assert_eq!(
+            out,
+            "<span font_family=\"monospace\" foreground=\"#ffffff\" background=\"#000000\">\
+             <span foreground=\"#ffffff\" background=\"#ffffff\" style=\"italic\" \
+             underline=\"single\">a&lt;&amp;&#39;b&#39;&gt;</span>\n</span>"
+        );
+
+        let mut out = String::new();
+        renderer.token(&mut out, style, "\x0c\x1b[0m\t");
+        assert!(out.ends_with(">\u{240c}\u{241b}[0m\t</span>"), "{}", out);
+    }
+}
@@ -0,0 +1,165 @@
+use super::{blend, theme_palette, Renderer};
+use crate::options::SyntectOptions;
+use std::fmt::Write;
+use syntect::highlighting::{Color, FontStyle, Style, Theme};
+
+/// An RTF document in a monospaced font, for pasting into word processors.
+///
+/// RTF wants every color in the color table at the top of the document, so the table holds
+/// the theme's colors and those known up front, such as the gutter and line highlight. Any
+/// other color is drawn as the closest one in the table.
+pub(crate) struct RtfRenderer {
+    colors: Vec<Color>,
+}
+
+impl RtfRenderer {
+    /// Builds the color table from the theme, the colors in the options, and `decorations`,
+    /// the opaque colors that the line output draws by itself.
+    pub(crate) fn new(theme: &Theme, options: &SyntectOptions, decorations: &[Color]) -> Self {
+        let background = theme.settings.background.unwrap_or(Color::WHITE);
+        let mut colors = theme_palette(theme);
+        let configured = [
+            options.match_foreground,
+            options.match_background,
+            options.diff_added_background,
+            options.diff_removed_background,
+        ];
+        let extra = configured
+            .into_iter()
+            .flatten()
+            .map(|color| blend(color, background))
+            .chain(decorations.iter().copied());
+        for color in extra {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+        RtfRenderer { colors }
+    }
+
+    /// The index of a color in the color table, which starts at 1.
+    fn color_index(&self, color: Color) -> usize {
+        let distance = |other: &Color| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(color.r, other.r) + d(color.g, other.g) + d(color.b, other.b)
+        };
+        let (index, _) = self
+            .colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, other)| distance(other))
+            .unwrap();
+        index + 1
+    }
+}
+
+fn escape_rtf(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\t' => out.push_str("\\tab "),
+            ' '..='~' => out.push(c),
+            _ => {
+                // Non-ASCII characters are written as signed UTF-16 code units, with `?` for
+                // readers that do not understand `\u`.
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let _ = write!(out, "\\u{}?", *unit as i16);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for RtfRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        out.push_str("{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern\\fcharset0 Courier New;}}\n");
+        out.push_str("{\\colortbl;");
+        for color in &self.colors {
+            let _ = write!(out, "\\red{}\\green{}\\blue{};", color.r, color.g, color.b);
+        }
+        out.push_str("}\n\\f0\\fs20\n");
+    }
+
+    fn token(&mut self, out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let foreground = self.color_index(blend(style.foreground, style.background));
+        let background = self.color_index(style.background);
+        // Word reads `\chcbpat`, other readers `\cb`.
+        let _ = write!(
+            out,
+            "{{\\cf{}\\chshdng0\\chcbpat{}\\cb{}",
+            foreground, background, background
+        );
+        for (flag, control) in [
+            (FontStyle::BOLD, "\\b"),
+            (FontStyle::ITALIC, "\\i"),
+            (FontStyle::UNDERLINE, "\\ul"),
+        ] {
+            if style.font_style.contains(flag) {
+                out.push_str(control);
+            }
+        }
+        out.push(' ');
+        escape_rtf(out, text);
+        out.push('}');
+    }
+
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        out.push_str("\\par");
+        out.push_str(line_ending);
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("}\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+
+    #[test]
+    fn test_rtf() {
+        let ts = ThemeSet::load_defaults();
+        let theme = &ts.themes["base16-ocean.dark"];
+        let mut renderer = RtfRenderer::new(theme, &SyntectOptions::default(), &[]);
+        let mut out = String::new();
+        renderer.begin_document(&mut out);
+        // The theme foreground and background come first in the color table.
+        assert!(
+            out.contains("{\\colortbl;\\red192\\green197\\blue206;\\red43\\green48\\blue59;"),
+            "{}",
+            out
+        );
+
+        let mut out = String::new();
+        let style = Style {
+            foreground: Color {
+                r: 0xc0,
+                g: 0xc5,
+                b: 0xcf,
+                a: 0xff,
+            },
+            background: Color {
+                r: 0x2b,
+                g: 0x30,
+                b: 0x3b,
+                a: 0xff,
+            },
+            font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        };
+        renderer.token(&mut out, style, "{é}\t😀");
+        renderer.end_line(&mut out, "\n");
+        assert_eq!(
+            out,
+            "{\\cf1\\chshdng0\\chcbpat2\\cb2\\b\\i \\{\\u233?\\}\\tab \\u-10179?\\u-8704?}\\par\n"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use super::html::escape_html;
+use super::text::xml_safe;
+use super::{blend, Renderer};
+use std::fmt::Write;
+use syntect::highlighting::{Color, FontStyle, Style};
+use unicode_width::UnicodeWidthStr;
+
+/// Advance of a monospace character, in ems.
+const CHAR_WIDTH: f64 = 0.6;
+
+/// The font size, line height and padding of SVG output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SvgLayout {
+    /// Font size in pixels.
+    pub(crate) font_size: f64,
+    /// Distance between baselines, as a multiple of the font size.
+    pub(crate) line_height: f64,
+    /// Space around the code, in pixels.
+    pub(crate) padding: f64,
+}
+
+impl Default for SvgLayout {
+    fn default() -> Self {
+        SvgLayout {
+            font_size: 14.0,
+            line_height: 1.4,
+            padding: 16.0,
+        }
+    }
+}
+
+/// An SVG image with one `<text>` element per row and one `<tspan>` per token, on a
+/// rectangle of the theme background.
+///
+/// Tokens with a background of their own get a rectangle behind them, placed by assuming a
+/// monospace font. The size of the image is only known at the end, so the root element gets
+/// it in `complete_document`.
+pub(crate) struct SvgRenderer {
+    layout: SvgLayout,
+    background: Color,
+    /// Rows written since the start of the document.
+    rows: usize,
+    /// Width of the widest row, in columns.
+    columns: usize,
+    /// Width of the current row so far, in columns.
+    column: usize,
+    rects: String,
+    text: String,
+}
+
+impl SvgRenderer {
+    pub(crate) fn new(layout: SvgLayout, background: Color) -> SvgRenderer {
+        SvgRenderer {
+            layout,
+            background,
+            rows: 0,
+            columns: 0,
+            column: 0,
+            rects: String::new(),
+            text: String::new(),
+        }
+    }
+
+    fn line_height(&self) -> f64 {
+        self.layout.font_size * self.layout.line_height
+    }
+
+    fn char_width(&self) -> f64 {
+        self.layout.font_size * CHAR_WIDTH
+    }
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Formats a length with at most two decimals.
+fn length(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+impl Renderer for SvgRenderer {
+    fn begin_document(&mut self, out: &mut String) {
+        self.rows = 0;
+        self.columns = 0;
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             font-family=\"ui-monospace, Menlo, Consolas, 'DejaVu Sans Mono', monospace\" \
+             font-size=\"{}\">",
+            length(self.layout.font_size)
+        );
+        let _ = writeln!(
+            out,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            css_color(self.background)
+        );
+    }
+
+    fn begin_line(&mut self, _out: &mut String, _line_number: usize) {
+        self.column = 0;
+        self.rects.clear();
+        self.text.clear();
+    }
+
+    fn token(&mut self, _out: &mut String, style: Style, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        // Left alone, control characters would make the image invalid XML.
+        let text = xml_safe(text);
+        let width = text.width();
+        if style.background != self.background {
+            let top = self.layout.padding + self.rows as f64 * self.line_height();
+            let _ = write!(
+                self.rects,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                length(self.layout.padding + self.column as f64 * self.char_width()),
+                length(top),
+                length(width as f64 * self.char_width()),
+                length(self.line_height()),
+                css_color(style.background)
+            );
+        }
+        self.column += width;
+
+        let foreground = blend(style.foreground, style.background);
+        let _ = write!(self.text, "<tspan fill=\"{}\"", css_color(foreground));
+        if style.font_style.contains(FontStyle::BOLD) {
+            self.text.push_str(" font-weight=\"bold\"");
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            self.text.push_str(" font-style=\"italic\"");
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            self.text.push_str(" text-decoration=\"underline\"");
+        }
+        self.text.push('>');
+        escape_html(&mut self.text, &text);
+        self.text.push_str("</tspan>");
+    }
+
+    fn end_line(&mut self, out: &mut String, line_ending: &str) {
+        // The baseline sits so that the glyphs are centered in the row.
+        let top = self.layout.padding + self.rows as f64 * self.line_height();
+        let baseline = top + (self.line_height() + self.layout.font_size * 0.7) / 2.0;
+        out.push_str(&self.rects);
+        let _ = write!(
+            out,
+            "<text x=\"{}\" y=\"{}\" xml:space=\"preserve\">{}</text>",
+            length(self.layout.padding),
+            length(baseline),
+            self.text
+        );
+        out.push_str(line_ending);
+        self.rows += 1;
+        self.columns = self.columns.max(self.column);
+    }
+
+    fn end_document(&mut self, out: &mut String) {
+        out.push_str("</svg>\n");
+    }
+
+    fn complete_document(&mut self, document: &mut String) {
+        let width = 2.0 * self.layout.padding + self.columns as f64 * self.char_width();
+        let height = 2.0 * self.layout.padding + self.rows as f64 * self.line_height();
+        if let Some(start) = document.find("<svg ") {
+            document.insert_str(
+                start + "<svg ".len(),
+                &format!(
+                    "width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" ",
+                    length(width),
+                    length(height)
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg() {
+        let background = Color::BLACK;
+        let mut renderer = SvgRenderer::new(
+            SvgLayout {
+                font_size: 10.0,
+                line_height: 2.0,
+                padding: 5.0,
+            },
+            background,
+        );
+        let style = Style {
+            foreground: Color::WHITE,
+            background,
+            font_style: FontStyle::BOLD,
+        };
+        let mut out = String::new();
+        renderer.begin_document(&mut out);
+        renderer.begin_line(&mut out, 1);
+        renderer.token(&mut out, style, "a<b");
+        renderer.token(
+            &mut out,
+            Style {
+                background: Color::WHITE,
+                ..style
+            },
+            "c",
+        );
+        renderer.end_line(&mut out, "\n");
+        renderer.end_document(&mut out);
+        renderer.complete_document(&mut out);
+
+        assert!(out.starts_with(
+            "<svg width=\"34\" height=\"30\" viewBox=\"0 0 34 30\" xmlns=\"http://www.w3.org/2000/svg\""
+        ));
+        assert!(out.contains(
+            "<rect x=\"23\" y=\"5\" width=\"6\" height=\"20\" fill=\"#ffffff\"/>\
+             <text x=\"5\" y=\"18.5\" xml:space=\"preserve\">\
+             <tspan fill=\"#ffffff\" font-weight=\"bold\">a&lt;b</tspan>"
+        ));
+        assert!(out.ends_with("</text>\n</svg>\n"));
+
+        let mut out = String::new();
+        renderer.begin_line(&mut out, 2);
+        renderer.token(&mut out, style, "\x0c\x1b[0m\t");
+        assert!(renderer.text.ends_with(">\u{240c}\u{241b}[0m\t</tspan>"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Rewriting of token text before it is rendered.
+
+use crate::options::ControlChars;
+use std::borrow::Cow;
+use syntect::highlighting::Style;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// A line of text split into styled tokens.
+pub(crate) type Tokens<'a> = Vec<(Style, Cow<'a, str>)>;
+
+fn is_control(c: char) -> bool {
+    c.is_control() && c != '\t'
+}
+
+/// Writes a control character in caret notation (`^[`) or as a Unicode control picture (`␛`).
+/// C1 controls have neither, so they are written as their code in angle brackets (`<85>`).
+fn push_control(out: &mut String, c: char, mode: ControlChars) {
+    let code = c as u32;
+    match (mode, code) {
+        (ControlChars::Caret, 0x00..=0x1f | 0x7f) => {
+            out.push('^');
+            out.push(char::from_u32(code ^ 0x40).unwrap());
+        }
+        (ControlChars::Pictures, 0x00..=0x1f) => out.push(char::from_u32(0x2400 + code).unwrap()),
+        (ControlChars::Pictures, 0x7f) => out.push('\u{2421}'),
+        _ => out.push_str(&format!("<{:02x}>", code)),
+    }
+}
+
+/// Writes the control characters that XML does not allow, which are the C0 controls other than
+/// tab, line feed and carriage return, as their Unicode control pictures.
+pub(crate) fn xml_safe(text: &str) -> Cow<'_, str> {
+    let forbidden = |c: char| c < ' ' && !matches!(c, '\t' | '\n' | '\r');
+    if !text.contains(forbidden) {
+        return Cow::Borrowed(text);
+    }
+    let mut safe = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if forbidden(c) {
+            push_control(&mut safe, c, ControlChars::Pictures);
+        } else {
+            safe.push(c);
+        }
+    }
+    Cow::Owned(safe)
+}
+
+/// Expands tabs to `tab_width` columns (0 keeps them) and makes control characters visible,
+/// keeping each piece of text in the style of the token it came from.
+pub(crate) fn make_visible<'a>(
+    regions: Vec<(Style, &'a str)>,
+    tab_width: usize,
+    control_chars: ControlChars,
+) -> Tokens<'a> {
+    let expand_tabs = tab_width > 0;
+    let show_controls = control_chars != ControlChars::Keep;
+    let mut column = 0;
+    regions
+        .into_iter()
+        .map(|(style, text)| {
+            let needs_rewrite = text
+                .chars()
+                .any(|c| (expand_tabs && c == '\t') || (show_controls && is_control(c)));
+            if !needs_rewrite {
+                column += text.width();
+                return (style, Cow::Borrowed(text));
+            }
+
+            let mut rewritten = String::with_capacity(text.len() + 8);
+            for c in text.chars() {
+                let start = rewritten.len();
+                if expand_tabs && c == '\t' {
+                    let spaces = tab_width - column % tab_width;
+                    rewritten.extend(std::iter::repeat_n(' ', spaces));
+                } else if show_controls && is_control(c) {
+                    push_control(&mut rewritten, c, control_chars);
+                } else {
+                    rewritten.push(c);
+                }
+                column += rewritten[start..].width();
+            }
+            (style, Cow::Owned(rewritten))
+        })
+        .collect()
+}
+
+/// Takes `range` out of a token's text, borrowing from the source when the text is borrowed.
+fn slice<'a>(text: &Cow<'a, str>, range: std::ops::Range<usize>) -> Cow<'a, str> {
+    match text {
+        Cow::Borrowed(text) => Cow::Borrowed(&text[range]),
+        Cow::Owned(text) => Cow::Owned(text[range].to_string()),
+    }
+}
+
+/// Splits a line into rows no wider than `width` display columns, counting East Asian wide
+/// characters as two columns. A token that crosses a row boundary is split, and its style
+/// carries over to the next row. There is always at least one row.
+pub(crate) fn wrap(tokens: Tokens<'_>, width: usize) -> Vec<Tokens<'_>> {
+    let mut rows = vec![Vec::new()];
+    let mut column = 0;
+    for (style, text) in tokens {
+        let mut start = 0;
+        for (index, c) in text.char_indices() {
+            let char_width = c.width().unwrap_or(0);
+            if column + char_width > width && column > 0 {
+                if index > start {
+                    rows.last_mut()
+                        .unwrap()
+                        .push((style, slice(&text, start..index)));
+                }
+                rows.push(Vec::new());
+                start = index;
+                column = 0;
+            }
+            column += char_width;
+        }
+        if start < text.len() || text.is_empty() {
+            rows.last_mut()
+                .unwrap()
+                .push((style, slice(&text, start..text.len())));
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(regions: &[(Style, Cow<'_, str>)]) -> Vec<String> {
+        regions.iter().map(|(_, text)| text.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        let style = Style::default();
+        let regions = make_visible(
+            vec![(style, "a\tb"), (style, "\tcd\t")],
+            4,
+            ControlChars::Keep,
+        );
+        assert_eq!(texts(&regions), ["a   b", "   cd  "]);
+    }
+
+    #[test]
+    fn test_control_chars() {
+        let style = Style::default();
+        let line = vec![(style, "\x1b[31m\t\x7f\u{85}")];
+
+        let caret = make_visible(line.clone(), 0, ControlChars::Caret);
+        assert_eq!(texts(&caret), ["^[[31m\t^?<85>"]);
+
+        let pictures = make_visible(line.clone(), 2, ControlChars::Pictures);
+        assert_eq!(texts(&pictures), ["\u{241b}[31m \u{2421}<85>"]);
+
+        let kept = make_visible(line, 0, ControlChars::Keep);
+        assert!(matches!(kept[0].1, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let plain = Style::default();
+        let bold = Style {
+            font_style: syntect::highlighting::FontStyle::BOLD,
+            ..plain
+        };
+        let tokens = vec![
+            (plain, Cow::Borrowed("abc")),
+            (bold, Cow::Borrowed("de日本")),
+            (plain, Cow::Borrowed("")),
+        ];
+
+        let rows = wrap(tokens, 4);
+        assert_eq!(rows[1][0].0, bold);
+        let rows: Vec<Vec<String>> = rows.iter().map(|row| texts(row)).collect();
+        assert_eq!(rows, [vec!["abc", "d"], vec!["e日"], vec!["本", ""]]);
+    }
+}
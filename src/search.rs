@@ -0,0 +1,320 @@
+//! Emphasis of search matches on top of the syntax colors.
+
+use crate::color::blend;
+use crate::options::SyntectOptions;
+use crate::{set_error, str_arg, SyntectFile, SyntectLines};
+use libc::c_char;
+use std::ops::Range;
+use syntect::highlighting::{Color, Style};
+use syntect::parsing::{Regex, Region};
+
+/// The search matches of a highlighter and how they are drawn.
+#[derive(Default)]
+pub(crate) struct Matches {
+    /// Byte ranges to emphasize in the next line only.
+    next_line: Vec<Range<usize>>,
+    /// A pattern emphasized in every line.
+    regex: Option<Regex>,
+    foreground: Option<Color>,
+    background: Option<Color>,
+}
+
+impl Matches {
+    pub(crate) fn set_options(&mut self, options: &SyntectOptions) {
+        self.foreground = options.match_foreground;
+        self.background = options.match_background;
+    }
+
+    /// Returns the sorted, disjoint ranges to emphasize in a line with the given content,
+    /// consuming the ranges set for it.
+    pub(crate) fn take_ranges(&mut self, content: &str) -> Vec<Range<usize>> {
+        let mut ranges = std::mem::take(&mut self.next_line);
+        if let Some(regex) = &self.regex {
+            let mut region = Region::new();
+            let mut pos = 0;
+            while pos <= content.len()
+                && regex.search(content, pos, content.len(), Some(&mut region))
+            {
+                let Some((start, end)) = region.pos(0) else {
+                    break;
+                };
+                ranges.push(start..end);
+                // Step over empty matches so the search always moves on.
+                pos = match content[end..].chars().next() {
+                    Some(c) if start == end => end + c.len_utf8(),
+                    None if start == end => break,
+                    _ => end,
+                };
+            }
+        }
+
+        // Clamp the ranges to the content and widen them to whole characters.
+        let floor = |mut i: usize| {
+            i = i.min(content.len());
+            while !content.is_char_boundary(i) {
+                i -= 1;
+            }
+            i
+        };
+        let ceil = |mut i: usize| {
+            i = i.min(content.len());
+            while !content.is_char_boundary(i) {
+                i += 1;
+            }
+            i
+        };
+        let mut ranges: Vec<Range<usize>> = ranges
+            .into_iter()
+            .map(|range| floor(range.start)..ceil(range.end))
+            .filter(|range| !range.is_empty())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// The style of matched text: the configured colors, or the syntax colors inverted.
+    pub(crate) fn emphasize(&self, style: Style) -> Style {
+        if self.foreground.is_none() && self.background.is_none() {
+            return Style {
+                foreground: style.background,
+                background: blend(style.foreground, style.background),
+                ..style
+            };
+        }
+        let background = self.background.map_or(style.background, |background| {
+            blend(background, style.background)
+        });
+        Style {
+            foreground: self.foreground.unwrap_or(style.foreground),
+            background,
+            ..style
+        }
+    }
+
+    /// Emphasizes the parts of the regions that fall in `ranges`.
+    pub(crate) fn overlay(&self, regions: &mut Vec<(Style, &str)>, ranges: &[Range<usize>]) {
+        if !ranges.is_empty() {
+            *regions = overlay(regions, ranges, |style| self.emphasize(style));
+        }
+    }
+}
+
+/// Splits the regions at the boundaries of `ranges` and restyles the pieces inside them.
+///
+/// This is `syntect::util::modify_range` for several ranges at once, with a function in place
+/// of a `StyleModifier` so that colors can be swapped. Empty regions are kept.
+pub(crate) fn overlay<'a>(
+    regions: &[(Style, &'a str)],
+    ranges: &[Range<usize>],
+    emphasize: impl Fn(Style) -> Style,
+) -> Vec<(Style, &'a str)> {
+    let mut out = Vec::with_capacity(regions.len() + ranges.len() * 2);
+    let mut ranges = ranges.iter().peekable();
+    let mut offset = 0;
+    for &(style, text) in regions {
+        let end = offset + text.len();
+        let mut pos = offset;
+        if text.is_empty() {
+            out.push((style, text));
+        }
+        while pos < end {
+            while ranges.next_if(|range| range.end <= pos).is_some() {}
+            let piece_end = match ranges.peek() {
+                Some(range) if range.start <= pos => {
+                    let piece_end = range.end.min(end);
+                    out.push((emphasize(style), &text[pos - offset..piece_end - offset]));
+                    pos = piece_end;
+                    continue;
+                }
+                Some(range) if range.start < end => range.start,
+                _ => end,
+            };
+            out.push((style, &text[pos - offset..piece_end - offset]));
+            pos = piece_end;
+        }
+        offset = end;
+    }
+    out
+}
+
+/// Converts `count` pairs of offsets received from C into ranges.
+fn ranges_arg(
+    ranges: *const usize,
+    count: usize,
+    error: *mut *const c_char,
+) -> Option<Vec<Range<usize>>> {
+    assert!(count == 0 || !ranges.is_null());
+    let offsets = match count {
+        0 => &[][..],
+        _ => unsafe { std::slice::from_raw_parts(ranges, count * 2) },
+    };
+    let ranges: Vec<Range<usize>> = offsets.chunks(2).map(|pair| pair[0]..pair[1]).collect();
+    if let Some(range) = ranges.iter().find(|range| range.start > range.end) {
+        set_error(
+            error,
+            format!("Invalid match range {}..{}", range.start, range.end),
+        );
+        return None;
+    }
+    Some(ranges)
+}
+
+/// Converts a pattern received from C, NULL meaning none.
+fn regex_arg(pattern: *const c_char, error: *mut *const c_char) -> Result<Option<Regex>, ()> {
+    if pattern.is_null() {
+        return Ok(None);
+    }
+    let pattern = str_arg(pattern, "Invalid pattern", error).ok_or(())?;
+    if let Some(err) = Regex::try_compile(pattern) {
+        set_error(error, format!("Invalid pattern '{}': {}", pattern, err));
+        return Err(());
+    }
+    Ok(Some(Regex::new(pattern.to_string())))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_lines_set_matches(
+    wrapper: *mut SyntectLines,
+    ranges: *const usize,
+    count: usize,
+    error: *mut *const c_char,
+) -> bool {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Some(ranges) = ranges_arg(ranges, count, error) else {
+        return false;
+    };
+
+    wrapper.matches.next_line = ranges;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_lines_set_match_regex(
+    wrapper: *mut SyntectLines,
+    pattern: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Ok(regex) = regex_arg(pattern, error) else {
+        return false;
+    };
+
+    wrapper.matches.regex = regex;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_file_set_matches(
+    wrapper: *mut SyntectFile,
+    ranges: *const usize,
+    count: usize,
+    error: *mut *const c_char,
+) -> bool {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Some(ranges) = ranges_arg(ranges, count, error) else {
+        return false;
+    };
+
+    wrapper.matches.next_line = ranges;
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_file_set_match_regex(
+    wrapper: *mut SyntectFile,
+    pattern: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Ok(regex) = regex_arg(pattern, error) else {
+        return false;
+    };
+
+    wrapper.matches.regex = regex;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color {
+        r: 0xff,
+        g: 0,
+        b: 0,
+        a: 0xff,
+    };
+
+    fn style(foreground: Color) -> Style {
+        Style {
+            foreground,
+            background: Color::WHITE,
+            ..Style::default()
+        }
+    }
+
+    #[test]
+    fn test_overlay() {
+        let regions = [(style(RED), "let"), (style(Color::BLACK), " x = 1;")];
+        let overlaid = overlay(&regions, &[2..5, 8..9], |s| Style {
+            background: Color::BLACK,
+            ..s
+        });
+        let pieces: Vec<&str> = overlaid.iter().map(|(_, text)| *text).collect();
+        assert_eq!(pieces, ["le", "t", " x", " = ", "1", ";"]);
+        let emphasized: Vec<bool> = overlaid
+            .iter()
+            .map(|(style, _)| style.background == Color::BLACK)
+            .collect();
+        assert_eq!(emphasized, [false, true, true, false, true, false]);
+        assert_eq!(overlaid[1].0.foreground, RED);
+    }
+
+    #[test]
+    fn test_take_ranges() {
+        let mut matches = Matches {
+            next_line: vec![5..6, 0..2, 1..3],
+            regex: Some(Regex::new("o+".to_string())),
+            ..Matches::default()
+        };
+        // Ranges are merged, and the one inside "é" is widened to the whole character.
+        assert_eq!(matches.take_ranges("foo é"), [0..3, 4..6]);
+        assert_eq!(matches.take_ranges("xoxo"), [1..2, 3..4]);
+
+        matches.regex = Some(Regex::new("x*".to_string()));
+        assert!(matches.take_ranges("ab").is_empty());
+    }
+
+    #[test]
+    fn test_emphasize() {
+        let mut matches = Matches::default();
+        let inverted = matches.emphasize(style(RED));
+        assert_eq!(inverted.foreground, Color::WHITE);
+        assert_eq!(inverted.background, RED);
+
+        matches.background = Some(Color::BLACK);
+        let custom = matches.emphasize(style(RED));
+        assert_eq!(custom.foreground, RED);
+        assert_eq!(custom.background, Color::BLACK);
+    }
+}
@@ -0,0 +1,224 @@
+//! Highlighted lines handed to C as styled spans instead of rendered text.
+
+use crate::buffer::{bytes_arg, output_buffer, syntect_free_buffer};
+use crate::{set_error, SyntectFile, SyntectLines};
+use libc::c_char;
+use std::ops::Range;
+use std::ptr;
+use syntect::highlighting::{Color, Style};
+
+/// An RGBA color.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntectColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl From<Color> for SyntectColor {
+    fn from(color: Color) -> Self {
+        SyntectColor {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+            a: color.a,
+        }
+    }
+}
+
+/// A piece of a highlighted line drawn in one style.
+#[repr(C)]
+pub struct SyntectSpan {
+    /// Byte offset of the start of the span in the line.
+    pub start: usize,
+    /// Byte offset of the end of the span in the line.
+    pub end: usize,
+    /// The text of the span, `end - start` bytes followed by a NUL.
+    pub text: *mut c_char,
+    pub foreground: SyntectColor,
+    pub background: SyntectColor,
+    /// A combination of `SyntectFontStyle` flags.
+    pub font_style: u8,
+    /// Whether the span is part of a search match.
+    pub matched: bool,
+}
+
+/// Turns the highlighted regions of a line with `content_len` bytes of content into spans,
+/// leaving out the line ending and empty regions.
+pub(crate) fn to_spans(
+    regions: &[(Style, &str)],
+    content_len: usize,
+    matches: &[Range<usize>],
+) -> Vec<SyntectSpan> {
+    let mut spans = Vec::with_capacity(regions.len());
+    let mut start = 0;
+    for &(style, text) in regions {
+        let text = &text[..text.len().min(content_len.saturating_sub(start))];
+        if text.is_empty() {
+            continue;
+        }
+        let mut len = 0;
+        spans.push(SyntectSpan {
+            start,
+            end: start + text.len(),
+            text: output_buffer(text.to_string(), &mut len) as *mut c_char,
+            foreground: style.foreground.into(),
+            background: style.background.into(),
+            font_style: style.font_style.bits(),
+            matched: matches.iter().any(|range| range.contains(&start)),
+        });
+        start += text.len();
+    }
+    spans
+}
+
+/// Hands spans to C. An empty line gives a non-NULL array with a count of 0.
+fn output_spans(spans: Vec<SyntectSpan>, count: *mut usize) -> *mut SyntectSpan {
+    assert!(!count.is_null());
+    unsafe {
+        *count = spans.len();
+    }
+    Box::into_raw(spans.into_boxed_slice()) as *mut SyntectSpan
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_highlight_text_line_spans(
+    wrapper: *mut SyntectLines,
+    data: *const c_char,
+    len: usize,
+    count: *mut usize,
+    error: *mut *const c_char,
+) -> *mut SyntectSpan {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+    let Some(line) = bytes_arg(data, len, "Invalid input line", error) else {
+        return ptr::null_mut();
+    };
+
+    match wrapper.highlight_line_spans(line) {
+        Ok(spans) => output_spans(spans, count),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_highlight_file_line_spans(
+    wrapper: *mut SyntectFile,
+    count: *mut usize,
+    error: *mut *const c_char,
+) -> *mut SyntectSpan {
+    let wrapper = unsafe {
+        assert!(!wrapper.is_null());
+        &mut *wrapper
+    };
+
+    match wrapper.highlight_next_line_spans() {
+        Ok(Some(spans)) => output_spans(spans, count),
+        Ok(None) => ptr::null_mut(),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_free_spans(spans: *mut SyntectSpan, count: usize) {
+    if !spans.is_null() {
+        unsafe {
+            let spans = Box::from_raw(ptr::slice_from_raw_parts_mut(spans, count));
+            for span in spans.iter() {
+                syntect_free_buffer(span.text, span.end - span.start);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options;
+    use crate::search::syntect_lines_set_match_regex;
+    use crate::{syntect_create_lines, syntect_free_lines, syntect_free_string};
+    use std::ffi::{CStr, CString};
+
+    #[test]
+    fn test_highlight_text_line_spans() {
+        let theme_name = CString::new("base16-ocean.dark").unwrap();
+        let mut error: *const c_char = ptr::null();
+        let lines = syntect_create_lines(theme_name.as_ptr(), &mut error);
+        assert!(!lines.is_null());
+        let pattern = CString::new("ma+in").unwrap();
+        assert!(syntect_lines_set_match_regex(
+            lines,
+            pattern.as_ptr(),
+            &mut error
+        ));
+
+        let line = "fn main() {}\n";
+        let mut count = 0;
+        let spans = syntect_highlight_text_line_spans(
+            lines,
+            line.as_ptr().cast(),
+            line.len(),
+            &mut count,
+            &mut error,
+        );
+        assert!(!spans.is_null());
+        let slice = unsafe { std::slice::from_raw_parts(spans, count) };
+        let texts: Vec<&str> = slice
+            .iter()
+            .map(|span| unsafe { CStr::from_ptr(span.text) }.to_str().unwrap())
+            .collect();
+        assert_eq!(texts.concat(), "fn main() {}");
+        let matched = slice.iter().find(|span| span.matched).unwrap();
+        assert_eq!((matched.start, matched.end), (3, 7));
+        assert_eq!(
+            texts[slice.iter().position(|span| span.matched).unwrap()],
+            "main"
+        );
+        // Without match colors the syntax colors are swapped.
+        assert_eq!(
+            matched.foreground,
+            SyntectColor::from(Color {
+                r: 0x2b,
+                g: 0x30,
+                b: 0x3b,
+                a: 0xff
+            })
+        );
+        syntect_free_spans(spans, count);
+
+        let options = options::syntect_options_new();
+        let background = CString::new("#ffff00").unwrap();
+        assert!(options::syntect_options_set_match_colors(
+            options,
+            ptr::null(),
+            background.as_ptr(),
+            &mut error
+        ));
+        options::syntect_lines_set_options(lines, options);
+        options::syntect_options_free(options);
+        let line = CString::new("fn main() {}").unwrap();
+        let highlighted = crate::syntect_highlight_text_line(lines, line.as_ptr(), &mut error);
+        let ansi = unsafe { CStr::from_ptr(highlighted) }.to_str().unwrap();
+        assert!(ansi.contains("\x1b[48;2;255;255;0m"), "{:?}", ansi);
+        syntect_free_string(highlighted as *mut c_char);
+
+        let invalid = CString::new("(").unwrap();
+        assert!(!syntect_lines_set_match_regex(
+            lines,
+            invalid.as_ptr(),
+            &mut error
+        ));
+        syntect_free_string(error as *mut c_char);
+        syntect_free_lines(lines);
+    }
+}
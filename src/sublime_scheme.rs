@@ -0,0 +1,240 @@
+//! Loader for Sublime Text `.sublime-color-scheme` JSON themes.
+
+use crate::color::parse_css_color;
+use crate::theme_builder::{format_color, parse_theme_item, set_theme_setting};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use syntect::highlighting::Theme;
+
+/// Loads a `.sublime-color-scheme` file into a syntect theme.
+pub(crate) fn load_sublime_color_scheme(path: &Path) -> Result<Theme, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error loading theme: {}", e))?;
+    let mut theme = parse_sublime_color_scheme(&text)?;
+    if theme.name.is_none() {
+        theme.name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(|stem| stem.to_string());
+    }
+    Ok(theme)
+}
+
+/// Parses the JSON text of a `.sublime-color-scheme` file.
+///
+/// Variables and `color()` mod functions are resolved while parsing. Global settings that
+/// syntect has no equivalent for are skipped, as syntect does for `.tmTheme` files.
+pub(crate) fn parse_sublime_color_scheme(text: &str) -> Result<Theme, String> {
+    let json = parse_json_with_comments(text)?;
+    let obj = json
+        .as_object()
+        .ok_or_else(|| "Color scheme is not a JSON object".to_string())?;
+
+    let variables: HashMap<String, String> = obj
+        .get("variables")
+        .and_then(Value::as_object)
+        .map(|vars| {
+            vars.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut theme = Theme {
+        name: obj.get("name").and_then(Value::as_str).map(String::from),
+        author: obj.get("author").and_then(Value::as_str).map(String::from),
+        ..Theme::default()
+    };
+
+    if let Some(globals) = obj.get("globals").and_then(Value::as_object) {
+        for (key, value) in globals {
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            let value = match parse_css_color(value, &variables) {
+                Ok(color) => format_color(color),
+                Err(_) => value.to_string(),
+            };
+            let _ = set_theme_setting(&mut theme.settings, key, Some(&value));
+        }
+    }
+
+    let rules = obj
+        .get("rules")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for rule in rules {
+        let Some(scope) = rule.get("scope").and_then(Value::as_str) else {
+            continue;
+        };
+        let rule_name = rule.get("name").and_then(Value::as_str).unwrap_or(scope);
+        let color = |key: &str| -> Result<Option<String>, String> {
+            // Gradient foregrounds are arrays; syntect can only use a single color.
+            let value = match rule.get(key) {
+                Some(Value::Array(values)) => values.first(),
+                value => value,
+            };
+            value
+                .and_then(Value::as_str)
+                .map(|value| {
+                    parse_css_color(value, &variables)
+                        .map(format_color)
+                        .map_err(|e| format!("Rule '{}': {}", rule_name, e))
+                })
+                .transpose()
+        };
+        let foreground = color("foreground")?;
+        let background = color("background")?;
+        let font_style = rule
+            .get("font_style")
+            .and_then(Value::as_str)
+            .map(font_style_words);
+
+        let item = parse_theme_item(
+            scope,
+            foreground.as_deref(),
+            background.as_deref(),
+            font_style.as_deref(),
+        )
+        .map_err(|e| format!("Rule '{}': {}", rule_name, e))?;
+        theme.scopes.push(item);
+    }
+
+    Ok(theme)
+}
+
+/// Keeps the font style keywords syntect understands, folding the underline variants into
+/// a plain underline. Sublime-only styles such as `glow` are dropped.
+fn font_style_words(font_style: &str) -> String {
+    font_style
+        .split_whitespace()
+        .filter_map(|word| match word {
+            "bold" | "italic" | "underline" => Some(word),
+            "stippled_underline" | "squiggly_underline" => Some("underline"),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses JSON that may contain the `//` and `/* */` comments and trailing commas that
+/// Sublime Text and VS Code accept in their settings files.
+pub(crate) fn parse_json_with_comments(text: &str) -> Result<Value, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                out.push(' ');
+            }
+            ']' | '}' => {
+                let trimmed = out.trim_end();
+                if trimmed.ends_with(',') {
+                    out.truncate(trimmed.len() - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    serde_json::from_str(&out).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::{Color, FontStyle};
+
+    #[test]
+    fn test_parse_sublime_color_scheme() {
+        let theme = parse_sublime_color_scheme(
+            r##"{
+                // A comment
+                "name": "Sample",
+                "variables": {
+                    "bg": "#202020",
+                    "accent": "hsl(0, 100%, 50%)",
+                },
+                "globals": {
+                    "background": "var(bg)",
+                    "line_highlight": "color(var(bg) blend(#ffffff 90%))",
+                    "brackets_options": "underline",
+                    "fold_marker": "red",
+                },
+                "rules": [
+                    {
+                        "name": "Comment",
+                        "scope": "comment, punctuation.definition.comment",
+                        "foreground": ["var(accent)", "blue"],
+                        "font_style": "italic glow",
+                    },
+                ],
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.name.as_deref(), Some("Sample"));
+        assert_eq!(
+            theme.settings.background,
+            Some(Color {
+                r: 0x20,
+                g: 0x20,
+                b: 0x20,
+                a: 0xff
+            })
+        );
+        assert!(theme.settings.line_highlight.is_some());
+        assert_eq!(theme.scopes.len(), 1);
+        assert_eq!(
+            theme.scopes[0].style.foreground,
+            Some(Color {
+                r: 0xff,
+                g: 0,
+                b: 0,
+                a: 0xff
+            })
+        );
+        assert_eq!(theme.scopes[0].style.font_style, Some(FontStyle::ITALIC));
+    }
+
+    #[test]
+    fn test_invalid_rule_color() {
+        let err = parse_sublime_color_scheme(
+            r#"{"rules": [{"name": "Broken", "scope": "comment", "foreground": "var(nope)"}]}"#,
+        )
+        .unwrap_err();
+        assert!(err.contains("Broken"), "{}", err);
+    }
+}
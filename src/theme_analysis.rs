@@ -0,0 +1,164 @@
+//! Light/dark classification and contrast checks for themes.
+
+use crate::color::{blend, contrast_ratio, relative_luminance};
+use crate::theme_builder::format_scope_selectors;
+use crate::SyntectTheme;
+use libc::c_char;
+use std::ffi::CString;
+use std::ptr;
+use syntect::highlighting::{Color, Theme};
+
+/// A theme rule whose foreground does not contrast enough with its background.
+#[repr(C)]
+pub struct SyntectContrastIssue {
+    /// Index of the rule in the theme.
+    pub rule_index: usize,
+    /// The scope selector of the rule.
+    pub scope: *mut c_char,
+    /// The WCAG contrast ratio of the rule, from 1 to 21.
+    pub ratio: f64,
+}
+
+/// The background syntect highlights with; themes without one are drawn on white.
+fn background(theme: &Theme) -> Color {
+    theme.settings.background.unwrap_or(Color::WHITE)
+}
+
+/// Whether the theme background is dark, that is, closer in contrast to black than to white.
+pub(crate) fn is_dark(theme: &Theme) -> bool {
+    let background = blend(background(theme), Color::WHITE);
+    contrast_ratio(background, Color::BLACK) < contrast_ratio(background, Color::WHITE)
+}
+
+/// Returns the index and contrast ratio of every rule whose foreground contrasts less than
+/// `min_ratio` with its background: the rule's own, or the theme's when the rule has none.
+pub(crate) fn low_contrast_rules(theme: &Theme, min_ratio: f64) -> Vec<(usize, f64)> {
+    let theme_background = blend(background(theme), Color::WHITE);
+    theme
+        .scopes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let foreground = item.style.foreground?;
+            let background = item
+                .style
+                .background
+                .map_or(theme_background, |bg| blend(bg, theme_background));
+            let ratio = contrast_ratio(blend(foreground, background), background);
+            (ratio < min_ratio).then_some((index, ratio))
+        })
+        .collect()
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_is_dark(theme: *const SyntectTheme) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    is_dark(theme)
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_get_background_luminance(theme: *const SyntectTheme) -> f64 {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    relative_luminance(blend(background(theme), Color::WHITE))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_find_low_contrast_rules(
+    theme: *const SyntectTheme,
+    min_ratio: f64,
+    count: *mut usize,
+    _error: *mut *const c_char,
+) -> *mut SyntectContrastIssue {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+    assert!(!count.is_null());
+
+    let issues: Box<[SyntectContrastIssue]> = low_contrast_rules(theme, min_ratio)
+        .into_iter()
+        .map(|(rule_index, ratio)| SyntectContrastIssue {
+            rule_index,
+            scope: CString::new(format_scope_selectors(&theme.scopes[rule_index].scope))
+                .unwrap()
+                .into_raw(),
+            ratio,
+        })
+        .collect();
+
+    unsafe {
+        *count = issues.len();
+    }
+    if issues.is_empty() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(issues) as *mut SyntectContrastIssue
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_free_contrast_issues(issues: *mut SyntectContrastIssue, count: usize) {
+    if !issues.is_null() {
+        unsafe {
+            let issues = Box::from_raw(ptr::slice_from_raw_parts_mut(issues, count));
+            for issue in issues.iter() {
+                drop(CString::from_raw(issue.scope));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_theme;
+    use crate::theme_builder::parse_theme_item;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_is_dark() {
+        let theme = load_theme("test/themes/base16-ocean.tmTheme", false).unwrap();
+        assert!(is_dark(&theme));
+        assert!(!is_dark(&Theme::default()));
+    }
+
+    #[test]
+    fn test_find_low_contrast_rules() {
+        let mut theme = Theme::default();
+        theme.settings.background = Some(Color::BLACK);
+        theme
+            .scopes
+            .push(parse_theme_item("comment", Some("#222222"), None, None).unwrap());
+        theme
+            .scopes
+            .push(parse_theme_item("string", Some("#EEEEEE"), None, None).unwrap());
+        theme
+            .scopes
+            .push(parse_theme_item("invalid", Some("#FFFFFF"), Some("#F0F0F0"), None).unwrap());
+        let theme = Box::into_raw(Box::new(SyntectTheme { theme }));
+
+        let mut count = 0;
+        let issues = syntect_theme_find_low_contrast_rules(theme, 4.5, &mut count, ptr::null_mut());
+        assert_eq!(count, 2);
+        let issues_slice = unsafe { std::slice::from_raw_parts(issues, count) };
+        assert_eq!(issues_slice[0].rule_index, 0);
+        assert_eq!(
+            unsafe { CStr::from_ptr(issues_slice[0].scope) }
+                .to_str()
+                .unwrap(),
+            "comment"
+        );
+        assert!(issues_slice[0].ratio < 1.5);
+        assert_eq!(issues_slice[1].rule_index, 2);
+
+        syntect_free_contrast_issues(issues, count);
+        crate::syntect_free_theme(theme);
+    }
+}
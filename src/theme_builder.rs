@@ -0,0 +1,520 @@
+use crate::{opt_str_arg, set_error, str_arg, SyntectTheme};
+use libc::c_char;
+use plist::{Dictionary, Value};
+use std::path::Path;
+use std::str::FromStr;
+use syntect::dumps::dump_to_file;
+use syntect::highlighting::{
+    Color, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSettings,
+    UnderlineOption,
+};
+
+macro_rules! theme_settings {
+    (
+        colors { $($color_key:literal $(| $color_alias:literal)* => $color_field:ident,)* }
+        underlines { $($ul_key:literal $(| $ul_alias:literal)* => $ul_field:ident,)* }
+        strings { $($str_key:literal $(| $str_alias:literal)* => $str_field:ident,)* }
+    ) => {
+        /// Sets a global theme setting from its tmTheme (camelCase) or snake_case key.
+        /// `None` clears the setting.
+        pub(crate) fn set_theme_setting(
+            settings: &mut ThemeSettings,
+            key: &str,
+            value: Option<&str>,
+        ) -> Result<(), String> {
+            match key {
+                $($color_key $(| $color_alias)* => {
+                    settings.$color_field = value.map(parse_color).transpose()?;
+                })*
+                $($ul_key $(| $ul_alias)* => {
+                    settings.$ul_field = value
+                        .map(|v| {
+                            UnderlineOption::from_str(v)
+                                .map_err(|_| format!("Invalid underline option '{}'", v))
+                        })
+                        .transpose()?;
+                })*
+                $($str_key $(| $str_alias)* => {
+                    settings.$str_field = value.map(|v| v.to_string());
+                })*
+                _ => return Err(format!("Unknown theme setting '{}'", key)),
+            }
+            Ok(())
+        }
+
+        /// Returns the global settings that are set, keyed by their tmTheme name.
+        pub(crate) fn theme_setting_entries(settings: &ThemeSettings) -> Vec<(&'static str, String)> {
+            let mut entries = Vec::new();
+            $(if let Some(color) = settings.$color_field {
+                entries.push(($color_key, format_color(color)));
+            })*
+            $(if let Some(option) = &settings.$ul_field {
+                if let Some(option) = format_underline_option(option) {
+                    entries.push(($ul_key, option.to_string()));
+                }
+            })*
+            $(if let Some(value) = &settings.$str_field {
+                entries.push(($str_key, value.clone()));
+            })*
+            entries
+        }
+    };
+}
+
+theme_settings! {
+    colors {
+        "foreground" => foreground,
+        "background" => background,
+        "caret" => caret,
+        "lineHighlight" | "line_highlight" => line_highlight,
+        "misspelling" => misspelling,
+        "minimapBorder" | "minimap_border" => minimap_border,
+        "accent" => accent,
+        "bracketContentsForeground" | "bracket_contents_foreground" => bracket_contents_foreground,
+        "bracketsForeground" | "brackets_foreground" => brackets_foreground,
+        "bracketsBackground" | "brackets_background" => brackets_background,
+        "tagsForeground" | "tags_foreground" => tags_foreground,
+        "highlight" => highlight,
+        "findHighlight" | "find_highlight" => find_highlight,
+        "findHighlightForeground" | "find_highlight_foreground" => find_highlight_foreground,
+        "gutter" => gutter,
+        "gutterForeground" | "gutter_foreground" => gutter_foreground,
+        "selection" => selection,
+        "selectionForeground" | "selection_foreground" => selection_foreground,
+        "selectionBorder" | "selection_border" => selection_border,
+        "inactiveSelection" | "inactive_selection" => inactive_selection,
+        "inactiveSelectionForeground" | "inactive_selection_foreground" => inactive_selection_foreground,
+        "guide" => guide,
+        "activeGuide" | "active_guide" => active_guide,
+        "stackGuide" | "stack_guide" => stack_guide,
+        "shadow" => shadow,
+    }
+    underlines {
+        "bracketContentsOptions" | "bracket_contents_options" => bracket_contents_options,
+        "bracketsOptions" | "brackets_options" => brackets_options,
+        "tagsOptions" | "tags_options" => tags_options,
+    }
+    strings {
+        "popupCss" | "popup_css" => popup_css,
+        "phantomCss" | "phantom_css" => phantom_css,
+    }
+}
+
+pub(crate) fn parse_color(s: &str) -> Result<Color, String> {
+    Color::from_str(s).map_err(|_| format!("Invalid color '{}'", s))
+}
+
+/// Formats a color as `#RRGGBB`, or `#RRGGBBAA` when it is not fully opaque.
+pub(crate) fn format_color(color: Color) -> String {
+    if color.a == 0xff {
+        format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+    } else {
+        format!(
+            "#{:02X}{:02X}{:02X}{:02X}",
+            color.r, color.g, color.b, color.a
+        )
+    }
+}
+
+pub(crate) fn format_font_style(font_style: FontStyle) -> String {
+    let mut names = Vec::new();
+    if font_style.contains(FontStyle::BOLD) {
+        names.push("bold");
+    }
+    if font_style.contains(FontStyle::ITALIC) {
+        names.push("italic");
+    }
+    if font_style.contains(FontStyle::UNDERLINE) {
+        names.push("underline");
+    }
+    names.join(" ")
+}
+
+fn format_underline_option(option: &UnderlineOption) -> Option<&'static str> {
+    match option {
+        UnderlineOption::None => None,
+        UnderlineOption::Underline => Some("underline"),
+        UnderlineOption::StippledUnderline => Some("stippled_underline"),
+        UnderlineOption::SquigglyUnderline => Some("squiggly_underline"),
+    }
+}
+
+/// Formats scope selectors back into the `a b - c, d` syntax they are parsed from.
+pub(crate) fn format_scope_selectors(selectors: &ScopeSelectors) -> String {
+    let join = |scopes: &[syntect::parsing::Scope]| {
+        scopes
+            .iter()
+            .map(|scope| scope.build_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    selectors
+        .selectors
+        .iter()
+        .map(|selector| {
+            let mut s = join(selector.path.as_slice());
+            for exclude in &selector.excludes {
+                s.push_str(" - ");
+                s.push_str(&join(exclude.as_slice()));
+            }
+            s
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Builds a theme rule from its tmTheme string representation.
+pub(crate) fn parse_theme_item(
+    scope: &str,
+    foreground: Option<&str>,
+    background: Option<&str>,
+    font_style: Option<&str>,
+) -> Result<ThemeItem, String> {
+    let scope = ScopeSelectors::from_str(scope)
+        .map_err(|e| format!("Invalid scope selector '{}': {}", scope, e))?;
+    let font_style = font_style
+        .map(|s| FontStyle::from_str(s).map_err(|_| format!("Invalid font style '{}'", s)))
+        .transpose()?;
+    Ok(ThemeItem {
+        scope,
+        style: StyleModifier {
+            foreground: foreground.map(parse_color).transpose()?,
+            background: background.map(parse_color).transpose()?,
+            font_style,
+        },
+    })
+}
+
+/// Converts a theme into the property list structure of a `.tmTheme` file.
+pub(crate) fn theme_to_plist(theme: &Theme) -> Value {
+    let mut root = Dictionary::new();
+    if let Some(name) = &theme.name {
+        root.insert("name".to_string(), Value::String(name.clone()));
+    }
+    if let Some(author) = &theme.author {
+        root.insert("author".to_string(), Value::String(author.clone()));
+    }
+
+    let mut globals = Dictionary::new();
+    for (key, value) in theme_setting_entries(&theme.settings) {
+        globals.insert(key.to_string(), Value::String(value));
+    }
+    let mut global_item = Dictionary::new();
+    global_item.insert("settings".to_string(), Value::Dictionary(globals));
+
+    let mut items = vec![Value::Dictionary(global_item)];
+    for item in &theme.scopes {
+        let mut style = Dictionary::new();
+        if let Some(foreground) = item.style.foreground {
+            style.insert(
+                "foreground".to_string(),
+                Value::String(format_color(foreground)),
+            );
+        }
+        if let Some(background) = item.style.background {
+            style.insert(
+                "background".to_string(),
+                Value::String(format_color(background)),
+            );
+        }
+        if let Some(font_style) = item.style.font_style {
+            style.insert(
+                "fontStyle".to_string(),
+                Value::String(format_font_style(font_style)),
+            );
+        }
+        let mut rule = Dictionary::new();
+        rule.insert(
+            "scope".to_string(),
+            Value::String(format_scope_selectors(&item.scope)),
+        );
+        rule.insert("settings".to_string(), Value::Dictionary(style));
+        items.push(Value::Dictionary(rule));
+    }
+    root.insert("settings".to_string(), Value::Array(items));
+
+    Value::Dictionary(root)
+}
+
+/// Saves a theme, picking the format from the extension of `path`.
+pub(crate) fn save_theme(theme: &Theme, path: &Path) -> Result<(), String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("tmdump") => {
+            dump_to_file(theme, path).map_err(|e| format!("Error dumping theme: {}", e))
+        }
+        Some("tmTheme") => theme_to_plist(theme)
+            .to_file_xml(path)
+            .map_err(|e| format!("Error writing theme: {}", e)),
+        _ => Err(format!(
+            "Unsupported theme file extension: {}",
+            path.display()
+        )),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_new(
+    name: *const c_char,
+    error: *mut *const c_char,
+) -> *mut SyntectTheme {
+    let name = match opt_str_arg(name, "Invalid theme name", error) {
+        Ok(name) => name,
+        Err(()) => return std::ptr::null_mut(),
+    };
+
+    let theme = Theme {
+        name: name.map(|name| name.to_string()),
+        ..Theme::default()
+    };
+
+    Box::into_raw(Box::new(SyntectTheme { theme }))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_set_setting(
+    theme: *mut SyntectTheme,
+    key: *const c_char,
+    value: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &mut (*theme).theme
+    };
+
+    let Some(key) = str_arg(key, "Invalid setting key", error) else {
+        return false;
+    };
+    let Ok(value) = opt_str_arg(value, "Invalid setting value", error) else {
+        return false;
+    };
+
+    let result = match key {
+        "name" => {
+            theme.name = value.map(|v| v.to_string());
+            Ok(())
+        }
+        "author" => {
+            theme.author = value.map(|v| v.to_string());
+            Ok(())
+        }
+        _ => set_theme_setting(&mut theme.settings, key, value),
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(err) => {
+            set_error(error, err);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_add_rule(
+    theme: *mut SyntectTheme,
+    scope: *const c_char,
+    foreground: *const c_char,
+    background: *const c_char,
+    font_style: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &mut (*theme).theme
+    };
+
+    let Some(scope) = str_arg(scope, "Invalid scope selector", error) else {
+        return false;
+    };
+    let Ok(foreground) = opt_str_arg(foreground, "Invalid foreground color", error) else {
+        return false;
+    };
+    let Ok(background) = opt_str_arg(background, "Invalid background color", error) else {
+        return false;
+    };
+    let Ok(font_style) = opt_str_arg(font_style, "Invalid font style", error) else {
+        return false;
+    };
+
+    match parse_theme_item(scope, foreground, background, font_style) {
+        Ok(item) => {
+            theme.scopes.push(item);
+            true
+        }
+        Err(err) => {
+            set_error(error, err);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_get_rule_count(theme: *const SyntectTheme) -> usize {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    theme.scopes.len()
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_remove_rule(
+    theme: *mut SyntectTheme,
+    index: usize,
+    error: *mut *const c_char,
+) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &mut (*theme).theme
+    };
+
+    if index >= theme.scopes.len() {
+        set_error(error, format!("Rule index {} out of range", index));
+        return false;
+    }
+    theme.scopes.remove(index);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_save_theme(
+    theme: *const SyntectTheme,
+    path: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    let Some(path) = str_arg(path, "Invalid theme path", error) else {
+        return false;
+    };
+
+    match save_theme(theme, Path::new(path)) {
+        Ok(()) => true,
+        Err(err) => {
+            set_error(error, err);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_theme, syntect_free_string, syntect_free_theme};
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    fn build_theme() -> *mut SyntectTheme {
+        let name = CString::new("High Contrast").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let theme = syntect_theme_new(name.as_ptr(), &mut error);
+        assert!(!theme.is_null(), "Failed to create theme");
+
+        let settings = [("background", "#000000"), ("line_highlight", "#202020")];
+        for (key, value) in settings {
+            let key = CString::new(key).unwrap();
+            let value = CString::new(value).unwrap();
+            assert!(syntect_theme_set_setting(
+                theme,
+                key.as_ptr(),
+                value.as_ptr(),
+                &mut error
+            ));
+        }
+
+        let scope = CString::new("comment, string - string.regexp").unwrap();
+        let foreground = CString::new("#FFFF00").unwrap();
+        let font_style = CString::new("bold italic").unwrap();
+        assert!(syntect_theme_add_rule(
+            theme,
+            scope.as_ptr(),
+            foreground.as_ptr(),
+            ptr::null(),
+            font_style.as_ptr(),
+            &mut error
+        ));
+        assert!(error.is_null());
+
+        theme
+    }
+
+    #[test]
+    fn test_build_theme() {
+        let theme = build_theme();
+        let built = unsafe { &(*theme).theme };
+
+        assert_eq!(built.name.as_deref(), Some("High Contrast"));
+        assert_eq!(built.settings.background, Some(Color::BLACK));
+        assert!(built.settings.line_highlight.is_some());
+        assert_eq!(syntect_theme_get_rule_count(theme), 1);
+        assert_eq!(
+            format_scope_selectors(&built.scopes[0].scope),
+            "comment, string - string.regexp"
+        );
+
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_invalid_rule() {
+        let theme = build_theme();
+        let scope = CString::new("comment").unwrap();
+        let foreground = CString::new("yellow-ish").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let added = syntect_theme_add_rule(
+            theme,
+            scope.as_ptr(),
+            foreground.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            &mut error,
+        );
+        assert!(!added);
+        assert!(!error.is_null(), "Expected an error");
+        assert_eq!(syntect_theme_get_rule_count(theme), 1);
+
+        syntect_free_string(error as *mut c_char);
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_save_theme() {
+        let theme = build_theme();
+        let dir = std::env::temp_dir();
+        let mut error: *const c_char = ptr::null();
+
+        for ext in ["tmTheme", "tmdump"] {
+            let path = dir.join(format!("syntect-c-test-save-theme.{}", ext));
+            let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+            let saved = syntect_save_theme(theme, c_path.as_ptr(), &mut error);
+            assert!(saved, "Unexpected error: {:?}", unsafe {
+                CStr::from_ptr(error).to_str().unwrap()
+            });
+
+            let loaded = if ext == "tmdump" {
+                syntect::dumps::from_dump_file(&path).unwrap()
+            } else {
+                load_theme(path.to_str().unwrap(), false).unwrap()
+            };
+            assert_eq!(loaded.name.as_deref(), Some("High Contrast"));
+            assert_eq!(loaded.settings.background, Some(Color::BLACK));
+            assert_eq!(loaded.scopes.len(), 1);
+            assert_eq!(
+                loaded.scopes[0].style.font_style,
+                Some(FontStyle::BOLD | FontStyle::ITALIC)
+            );
+
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        syntect_free_theme(theme);
+    }
+}
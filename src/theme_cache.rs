@@ -0,0 +1,299 @@
+//! On-disk cache of parsed themes.
+//!
+//! Each cached theme is stored in the cache directory as a `.tmdump` file, next to a small
+//! `.meta` file recording the modification time, length and content hash of its source. An
+//! entry is reused while the source keeps the same modification time and length, or, when
+//! those changed, while its contents still hash the same.
+
+use crate::{opt_str_arg, set_error};
+use libc::c_char;
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::RwLock;
+use std::time::UNIX_EPOCH;
+use syntect::dumps::{dump_to_file, from_dump_file};
+use syntect::highlighting::Theme;
+
+/// The cache directory set with `syntect_set_theme_cache_dir`, or `None` for the default.
+static CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// First line of every `.meta` file; entries written by another format version are ignored.
+const META_HEADER: &str = "syntect-c theme cache 1";
+
+/// What a cache entry knows about the source file it was built from.
+#[derive(Debug, PartialEq)]
+struct Fingerprint {
+    mtime: (u64, u32),
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    fn parse(text: &str) -> Option<Fingerprint> {
+        let mut lines = text.lines();
+        if lines.next()? != META_HEADER {
+            return None;
+        }
+        let mut fields = lines.next()?.split_whitespace();
+        let fingerprint = Fingerprint {
+            mtime: (fields.next()?.parse().ok()?, fields.next()?.parse().ok()?),
+            len: fields.next()?.parse().ok()?,
+            hash: u64::from_str_radix(fields.next()?, 16).ok()?,
+        };
+        Some(fingerprint)
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}\n{} {} {} {:016x}\n",
+            META_HEADER, self.mtime.0, self.mtime.1, self.len, self.hash
+        )
+    }
+}
+
+/// 64-bit FNV-1a, used both for content hashes and for naming cache entries.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn default_cache_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let base = var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(base.join("syntect-c"))
+}
+
+/// Returns the directory cached themes are written to, if one can be determined.
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let configured = CACHE_DIR.read().unwrap_or_else(|e| e.into_inner()).clone();
+    configured.or_else(default_cache_dir)
+}
+
+/// Points the cache at a temporary directory, so that tests loading themes with caching
+/// enabled do not write to the user's cache.
+#[cfg(test)]
+pub(crate) fn use_test_cache_dir() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        let dir = std::env::temp_dir().join("syntect-c-test-cache");
+        *CACHE_DIR.write().unwrap_or_else(|e| e.into_inner()) = Some(dir);
+    });
+}
+
+/// Loads a theme through the cache, parsing it with `read` when no fresh entry exists.
+pub(crate) fn load_cached(
+    path: &Path,
+    read: fn(&Path) -> Result<Theme, String>,
+) -> Result<Theme, String> {
+    if !path.exists() {
+        // A theme may be shipped as a bare `.tmdump` without its source.
+        let dump = path.with_extension("tmdump");
+        if dump.exists() {
+            return from_dump_file(dump).map_err(|e| format!("Error loading from cache: {}", e));
+        }
+        return read(path);
+    }
+    match cache_dir() {
+        Some(dir) => load_cached_in(&dir, path, read),
+        None => read(path),
+    }
+}
+
+fn load_cached_in(
+    dir: &Path,
+    path: &Path,
+    read: fn(&Path) -> Result<Theme, String>,
+) -> Result<Theme, String> {
+    let error = |e: std::io::Error| format!("Error loading theme: {}", e);
+    let metadata = fs::metadata(path).map_err(error)?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| (time.as_secs(), time.subsec_nanos()))
+        .unwrap_or_default();
+
+    let source = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let key = format!("theme-{:016x}", fnv1a(source.to_string_lossy().as_bytes()));
+    let dump_path = dir.join(format!("{}.tmdump", key));
+    let meta_path = dir.join(format!("{}.meta", key));
+
+    let cached = fs::read_to_string(&meta_path)
+        .ok()
+        .and_then(|text| Fingerprint::parse(&text));
+    let mut hash = None;
+    if let Some(cached) = cached {
+        let unchanged = cached.mtime == mtime && cached.len == metadata.len();
+        if !unchanged {
+            hash = Some(fnv1a(&fs::read(path).map_err(error)?));
+        }
+        if unchanged || hash == Some(cached.hash) {
+            // A damaged entry is rebuilt rather than reported.
+            if let Ok(theme) = from_dump_file(&dump_path) {
+                if !unchanged {
+                    let fingerprint = Fingerprint {
+                        mtime,
+                        len: metadata.len(),
+                        hash: cached.hash,
+                    };
+                    let _ = fs::write(&meta_path, fingerprint.format());
+                }
+                return Ok(theme);
+            }
+        }
+    }
+
+    // Hash before parsing, so that an edit made in between invalidates the entry next time.
+    let hash = match hash {
+        Some(hash) => hash,
+        None => fnv1a(&fs::read(path).map_err(error)?),
+    };
+    let theme = read(path)?;
+    let fingerprint = Fingerprint {
+        mtime,
+        len: metadata.len(),
+        hash,
+    };
+    // The cache only saves time, so an unwritable cache directory is not an error.
+    let _ = fs::create_dir_all(dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| dump_to_file(&theme, &dump_path).map_err(|e| e.to_string()))
+        .and_then(|_| fs::write(&meta_path, fingerprint.format()).map_err(|e| e.to_string()));
+    Ok(theme)
+}
+
+/// Removes the cache entries in `dir`, leaving any other files alone.
+fn clear_cache_in(dir: &Path) -> Result<(), String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Error reading cache directory: {}", e)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Error reading cache directory: {}", e))?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("theme-") && (name.ends_with(".tmdump") || name.ends_with(".meta")) {
+            fs::remove_file(entry.path())
+                .map_err(|e| format!("Error removing {}: {}", entry.path().display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_set_theme_cache_dir(
+    dir: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let Ok(dir) = opt_str_arg(dir, "Invalid cache directory", error) else {
+        return false;
+    };
+
+    *CACHE_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir.map(PathBuf::from);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_get_theme_cache_dir(error: *mut *const c_char) -> *const c_char {
+    match cache_dir().and_then(|dir| CString::new(dir.to_string_lossy().into_owned()).ok()) {
+        Some(dir) => dir.into_raw(),
+        None => {
+            set_error(error, "No theme cache directory available");
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_clear_theme_cache(error: *mut *const c_char) -> bool {
+    let Some(dir) = cache_dir() else {
+        return true;
+    };
+
+    match clear_cache_in(&dir) {
+        Ok(()) => true,
+        Err(err) => {
+            set_error(error, err);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    static READS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counting_read(path: &Path) -> Result<Theme, String> {
+        READS.fetch_add(1, Ordering::SeqCst);
+        crate::read_theme(path)
+    }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let dir = std::env::temp_dir().join("syntect-c-test-theme-cache");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = dir.join("cache");
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("ocean.tmTheme");
+        let original = fs::read_to_string("test/themes/base16-ocean.tmTheme").unwrap();
+        fs::write(&source, &original).unwrap();
+        let set_mtime = |secs: u64| {
+            fs::File::options()
+                .write(true)
+                .open(&source)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+        set_mtime(1_000_000);
+
+        let first = load_cached_in(&cache, &source, counting_read).unwrap();
+        let reads = READS.load(Ordering::SeqCst);
+        assert_eq!(
+            load_cached_in(&cache, &source, counting_read).unwrap(),
+            first
+        );
+        assert_eq!(READS.load(Ordering::SeqCst), reads);
+
+        // Touching the file without changing it keeps the entry.
+        set_mtime(2_000_000);
+        assert_eq!(
+            load_cached_in(&cache, &source, counting_read).unwrap(),
+            first
+        );
+        assert_eq!(READS.load(Ordering::SeqCst), reads);
+
+        fs::write(&source, original.replace("Base16 Ocean", "Edited Ocean")).unwrap();
+        set_mtime(3_000_000);
+        let edited = load_cached_in(&cache, &source, counting_read).unwrap();
+        assert_eq!(edited.name.as_deref(), Some("Edited Ocean"));
+        assert_eq!(READS.load(Ordering::SeqCst), reads + 1);
+
+        clear_cache_in(&cache).unwrap();
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_format() {
+        let fingerprint = Fingerprint {
+            mtime: (1, 2),
+            len: 3,
+            hash: 0xdeadbeef,
+        };
+        assert_eq!(Fingerprint::parse(&fingerprint.format()), Some(fingerprint));
+        assert_eq!(Fingerprint::parse("other format\n1 2 3 4\n"), None);
+    }
+}
@@ -0,0 +1,365 @@
+//! Exporters that write a theme out as tmTheme XML, a JSON color scheme, CSS variables or
+//! LaTeX color definitions.
+
+use crate::render::latex_preamble;
+use crate::theme_builder::{
+    format_color, format_font_style, format_scope_selectors, theme_setting_entries, theme_to_plist,
+};
+use crate::{opt_str_arg, output_string, set_error, SyntectTheme};
+use libc::c_char;
+use serde_json::{json, Map, Value};
+use std::ptr;
+use syntect::highlighting::{FontStyle, Theme};
+
+/// Serializes a theme as a TextMate `.tmTheme` XML document.
+pub(crate) fn theme_to_tmtheme(theme: &Theme) -> Result<String, String> {
+    let mut buf = Vec::new();
+    theme_to_plist(theme)
+        .to_writer_xml(&mut buf)
+        .map_err(|e| format!("Error writing theme: {}", e))?;
+    String::from_utf8(buf).map_err(|e| format!("Error writing theme: {}", e))
+}
+
+/// Serializes a theme as JSON in the layout of a `.sublime-color-scheme` file, so that the
+/// result can be loaded back with `syntect_load_theme`.
+pub(crate) fn theme_to_json(theme: &Theme) -> String {
+    let mut root = Map::new();
+    if let Some(name) = &theme.name {
+        root.insert("name".to_string(), json!(name));
+    }
+    if let Some(author) = &theme.author {
+        root.insert("author".to_string(), json!(author));
+    }
+
+    let globals: Map<String, Value> = theme_setting_entries(&theme.settings)
+        .into_iter()
+        .map(|(key, value)| (snake_case(key), json!(value)))
+        .collect();
+    root.insert("globals".to_string(), Value::Object(globals));
+
+    let rules = theme
+        .scopes
+        .iter()
+        .map(|item| {
+            let mut rule = Map::new();
+            rule.insert(
+                "scope".to_string(),
+                json!(format_scope_selectors(&item.scope)),
+            );
+            if let Some(foreground) = item.style.foreground {
+                rule.insert("foreground".to_string(), json!(format_color(foreground)));
+            }
+            if let Some(background) = item.style.background {
+                rule.insert("background".to_string(), json!(format_color(background)));
+            }
+            if let Some(font_style) = item.style.font_style {
+                rule.insert(
+                    "font_style".to_string(),
+                    json!(format_font_style(font_style)),
+                );
+            }
+            Value::Object(rule)
+        })
+        .collect();
+    root.insert("rules".to_string(), Value::Array(rules));
+
+    // Serializing a tree of strings cannot fail.
+    serde_json::to_string_pretty(&Value::Object(root)).unwrap()
+}
+
+/// Renders a theme as a block of CSS custom properties under `selector`.
+///
+/// Global settings become `--syntect-<setting>`; each scope selector of a rule becomes
+/// `--syntect-<scope>-foreground`, `-background`, `-font-weight`, `-font-style` and
+/// `-text-decoration`, with the scopes of a descendant selector joined by `--`. Selectors with
+/// excludes (`source - comment`) have no name of their own and are left out, as are the
+/// `popup_css` and `phantom_css` style sheets. When several selectors get the same name, the
+/// later one wins, as it does when highlighting.
+pub(crate) fn theme_to_css_variables(theme: &Theme, selector: &str) -> String {
+    let mut properties: Vec<(String, String)> = Vec::new();
+    let mut set = |name: String, value: String| match properties
+        .iter_mut()
+        .find(|(existing, _)| *existing == name)
+    {
+        Some(property) => property.1 = value,
+        None => properties.push((name, value)),
+    };
+
+    for (key, value) in theme_setting_entries(&theme.settings) {
+        if matches!(key, "popupCss" | "phantomCss") {
+            continue;
+        }
+        set(
+            format!("--syntect-{}", snake_case(key).replace('_', "-")),
+            css_value(&value),
+        );
+    }
+
+    for item in &theme.scopes {
+        for scope_selector in &item.scope.selectors {
+            if !scope_selector.excludes.is_empty() {
+                continue;
+            }
+            let name = scope_selector
+                .path
+                .as_slice()
+                .iter()
+                .map(|scope| css_identifier(&scope.build_string()))
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>()
+                .join("--");
+            if name.is_empty() {
+                continue;
+            }
+            let prefix = format!("--syntect-{}", name);
+            if let Some(foreground) = item.style.foreground {
+                set(format!("{}-foreground", prefix), format_color(foreground));
+            }
+            if let Some(background) = item.style.background {
+                set(format!("{}-background", prefix), format_color(background));
+            }
+            if let Some(font_style) = item.style.font_style {
+                let pick = |flag: FontStyle, on: &str, off: &str| {
+                    if font_style.contains(flag) { on } else { off }.to_string()
+                };
+                set(
+                    format!("{}-font-weight", prefix),
+                    pick(FontStyle::BOLD, "bold", "normal"),
+                );
+                set(
+                    format!("{}-font-style", prefix),
+                    pick(FontStyle::ITALIC, "italic", "normal"),
+                );
+                set(
+                    format!("{}-text-decoration", prefix),
+                    pick(FontStyle::UNDERLINE, "underline", "none"),
+                );
+            }
+        }
+    }
+
+    let mut css = format!("{} {{\n", selector);
+    for (name, value) in properties {
+        css.push_str(&format!("  {}: {};\n", name, value));
+    }
+    css.push_str("}\n");
+    css
+}
+
+/// Writes a setting value as it is when it is a color or a keyword, and as a CSS string
+/// otherwise, so that it cannot end the declaration.
+fn css_value(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || matches!(c, '#' | '-' | '_');
+    if !value.is_empty() && value.chars().all(plain) {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\{:x} ", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Turns a scope such as `string.quoted` into `string-quoted`.
+fn css_identifier(scope: &str) -> String {
+    let mapped: String = scope
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    mapped
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Converts a tmTheme setting key (`lineHighlight`) to its snake_case form (`line_highlight`).
+fn snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_to_tmtheme(
+    theme: *const SyntectTheme,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    match theme_to_tmtheme(theme) {
+        Ok(xml) => output_string(xml, error),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_to_json(
+    theme: *const SyntectTheme,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    output_string(theme_to_json(theme), error)
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_to_css_variables(
+    theme: *const SyntectTheme,
+    selector: *const c_char,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+    let Ok(selector) = opt_str_arg(selector, "Invalid CSS selector", error) else {
+        return ptr::null();
+    };
+
+    output_string(
+        theme_to_css_variables(theme, selector.unwrap_or(":root")),
+        error,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_to_latex_preamble(
+    theme: *const SyntectTheme,
+    error: *mut *const c_char,
+) -> *const c_char {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+
+    output_string(latex_preamble(theme), error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_theme;
+    use crate::sublime_scheme::parse_sublime_color_scheme;
+    use crate::theme_builder::parse_theme_item;
+    use std::io::Cursor;
+    use syntect::highlighting::{Color, ThemeSet};
+
+    #[test]
+    fn test_round_trip() {
+        let theme = load_theme("test/themes/base16-ocean.tmTheme", false).unwrap();
+
+        let xml = theme_to_tmtheme(&theme).unwrap();
+        let from_xml = ThemeSet::load_from_reader(&mut Cursor::new(xml)).unwrap();
+        assert_eq!(from_xml, theme);
+
+        let from_json = parse_sublime_color_scheme(&theme_to_json(&theme)).unwrap();
+        assert_eq!(from_json, theme);
+    }
+
+    #[test]
+    fn test_css_value() {
+        assert_eq!(css_value("#20202080"), "#20202080");
+        assert_eq!(css_value("stippled_underline"), "stippled_underline");
+        assert_eq!(css_value("a; \"b\"\n"), "\"a; \\\"b\\\"\\a \"");
+    }
+
+    #[test]
+    fn test_css_variables() {
+        let mut theme = Theme::default();
+        theme.settings.background = Some(Color::BLACK);
+        theme.settings.line_highlight = Some(Color {
+            r: 0x20,
+            g: 0x20,
+            b: 0x20,
+            a: 0x80,
+        });
+        theme.settings.popup_css = Some("html { color: red; }".to_string());
+        theme.settings.phantom_css = Some("red".to_string());
+        theme.scopes.push(
+            parse_theme_item(
+                "comment, string - string.regexp",
+                Some("#FFFF00"),
+                None,
+                None,
+            )
+            .unwrap(),
+        );
+        theme
+            .scopes
+            .push(parse_theme_item("comment", None, None, Some("italic")).unwrap());
+        theme
+            .scopes
+            .push(parse_theme_item("meta.tag string", Some("#00FF00"), None, None).unwrap());
+        theme
+            .scopes
+            .push(parse_theme_item("meta-tag.string", Some("#0000FF"), None, None).unwrap());
+
+        let css = theme_to_css_variables(&theme, ".code");
+        assert!(css.starts_with(".code {\n"), "{}", css);
+        assert!(!css.contains("css"), "{}", css);
+        assert!(
+            css.contains("  --syntect-background: #000000;\n"),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains("  --syntect-line-highlight: #20202080;\n"),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains("  --syntect-comment-foreground: #FFFF00;\n"),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains("  --syntect-comment-font-style: italic;\n"),
+            "{}",
+            css
+        );
+        // `string - string.regexp` has excludes, so it gets no variables.
+        assert!(!css.contains("--syntect-string-"), "{}", css);
+        assert!(
+            css.contains("  --syntect-meta-tag--string-foreground: #00FF00;\n"),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains("  --syntect-meta-tag-string-foreground: #0000FF;\n"),
+            "{}",
+            css
+        );
+        assert!(!css.contains("popup"), "{}", css);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::theme_builder::{set_theme_setting, theme_setting_entries};
+use crate::{load_theme, set_error, str_arg, SyntectTheme};
+use libc::c_char;
+use std::ptr;
+use syntect::highlighting::{ScopeSelectors, Theme, ThemeItem};
+
+/// Layers `overrides` on top of `base`, returning the derived theme.
+///
+/// Global settings that are set in `overrides` replace those of `base`. Each override rule is
+/// appended, and its style is also applied to the base rules it matches, so that a more
+/// specific base rule (`comment.line`) cannot win over an override for `comment`.
+pub(crate) fn derive_theme(base: &Theme, overrides: &Theme) -> Theme {
+    let mut theme = base.clone();
+
+    if overrides.name.is_some() {
+        theme.name = overrides.name.clone();
+    }
+    if overrides.author.is_some() {
+        theme.author = overrides.author.clone();
+    }
+    for (key, value) in theme_setting_entries(&overrides.settings) {
+        // The entries were produced from valid settings, so they always parse back.
+        let _ = set_theme_setting(&mut theme.settings, key, Some(&value));
+    }
+
+    for item in &overrides.scopes {
+        let mut scopes = Vec::with_capacity(theme.scopes.len() + 1);
+        for existing in theme.scopes.drain(..) {
+            let (matched, unmatched): (Vec<_>, Vec<_>) = existing
+                .scope
+                .selectors
+                .iter()
+                .cloned()
+                .partition(|selector| item.scope.does_match(selector.path.as_slice()).is_some());
+
+            if matched.is_empty() {
+                scopes.push(existing);
+                continue;
+            }
+            if !unmatched.is_empty() {
+                scopes.push(ThemeItem {
+                    scope: ScopeSelectors {
+                        selectors: unmatched,
+                    },
+                    style: existing.style,
+                });
+            }
+            scopes.push(ThemeItem {
+                scope: ScopeSelectors { selectors: matched },
+                style: existing.style.apply(item.style),
+            });
+        }
+        scopes.push(item.clone());
+        theme.scopes = scopes;
+    }
+
+    theme
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_derive(
+    base: *const SyntectTheme,
+    overrides: *const SyntectTheme,
+    _error: *mut *const c_char,
+) -> *mut SyntectTheme {
+    let base = unsafe {
+        assert!(!base.is_null());
+        &(*base).theme
+    };
+    let overrides = unsafe {
+        assert!(!overrides.is_null());
+        &(*overrides).theme
+    };
+
+    let theme = derive_theme(base, overrides);
+
+    Box::into_raw(Box::new(SyntectTheme { theme }))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_derive_from_file(
+    base: *const SyntectTheme,
+    override_path: *const c_char,
+    error: *mut *const c_char,
+) -> *mut SyntectTheme {
+    let base = unsafe {
+        assert!(!base.is_null());
+        &(*base).theme
+    };
+
+    let Some(override_path) = str_arg(override_path, "Invalid override path", error) else {
+        return ptr::null_mut();
+    };
+
+    match load_theme(override_path, false) {
+        Ok(overrides) => Box::into_raw(Box::new(SyntectTheme {
+            theme: derive_theme(base, &overrides),
+        })),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_builder::parse_theme_item;
+    use crate::{syntect_free_theme, syntect_load_theme};
+    use std::ffi::{CStr, CString};
+    use std::str::FromStr;
+    use syntect::easy::HighlightLines;
+    use syntect::highlighting::{Color, FontStyle};
+    use syntect::parsing::SyntaxSet;
+
+    fn comment_style(theme: &Theme) -> syntect::highlighting::Style {
+        let ss = SyntaxSet::load_defaults_newlines();
+        let syntax = ss.find_syntax_by_extension("rs").unwrap();
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let regions = highlighter.highlight_line("// hello\n", &ss).unwrap();
+        regions
+            .iter()
+            .find(|(_, text)| text.contains("hello"))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_override_beats_specific_rule() {
+        let mut base = Theme::default();
+        base.scopes
+            .push(parse_theme_item("comment.line", Some("#FF0000"), None, Some("italic")).unwrap());
+        base.scopes
+            .push(parse_theme_item("comment.line, string", Some("#00FF00"), None, None).unwrap());
+
+        let mut overrides = Theme::default();
+        overrides
+            .scopes
+            .push(parse_theme_item("comment", Some("#FFFFFF"), None, None).unwrap());
+        overrides.settings.background = Some(Color::BLACK);
+
+        let theme = derive_theme(&base, &overrides);
+
+        let style = comment_style(&theme);
+        assert_eq!(style.foreground, Color::WHITE);
+        assert_eq!(style.font_style, FontStyle::ITALIC);
+        assert_eq!(theme.settings.background, Some(Color::BLACK));
+
+        // The string half of the shared rule keeps its original color.
+        let string_rule = theme
+            .scopes
+            .iter()
+            .find(|item| item.scope == ScopeSelectors::from_str("string").unwrap())
+            .unwrap();
+        assert_eq!(
+            string_rule.style.foreground,
+            Some(Color {
+                r: 0,
+                g: 0xFF,
+                b: 0,
+                a: 0xFF
+            })
+        );
+    }
+
+    #[test]
+    fn test_theme_derive_from_file() {
+        let theme_path = CString::new("test/themes/base16-ocean.tmTheme").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let base = syntect_load_theme(theme_path.as_ptr(), false, &mut error);
+        assert!(!base.is_null(), "Failed to load theme");
+
+        let derived = syntect_theme_derive_from_file(base, theme_path.as_ptr(), &mut error);
+        assert!(!derived.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+        let (base_theme, derived_theme) = unsafe { (&(*base).theme, &(*derived).theme) };
+        assert_eq!(
+            comment_style(base_theme).foreground,
+            comment_style(derived_theme).foreground
+        );
+
+        syntect_free_theme(derived);
+        syntect_free_theme(base);
+    }
+}
@@ -0,0 +1,136 @@
+//! Registry of named themes that `syntect_create_file` and `syntect_create_lines` can use.
+
+use crate::{set_error, str_arg, SyntectTheme, SyntectThemeSet};
+use libc::c_char;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use syntect::highlighting::Theme;
+
+static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<Theme>>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<Theme>>> {
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Returns the theme registered under `name`, if any.
+///
+/// Highlighters keep the returned `Arc`, so unregistering or replacing a theme does not affect
+/// highlighters that were already created with it.
+pub(crate) fn registered_theme(name: &str) -> Option<Arc<Theme>> {
+    let themes = registry().read().unwrap_or_else(|e| e.into_inner());
+    themes.get(name).cloned()
+}
+
+fn register(name: &str, theme: Theme) {
+    let mut themes = registry().write().unwrap_or_else(|e| e.into_inner());
+    themes.insert(name.to_string(), Arc::new(theme));
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_register_theme(
+    theme_name: *const c_char,
+    theme: *const SyntectTheme,
+    error: *mut *const c_char,
+) -> bool {
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+    let Some(theme_name) = str_arg(theme_name, "Invalid theme name", error) else {
+        return false;
+    };
+
+    register(theme_name, theme.clone());
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_register_theme_set(
+    theme_set: *const SyntectThemeSet,
+    _error: *mut *const c_char,
+) -> bool {
+    let theme_set = unsafe {
+        assert!(!theme_set.is_null());
+        &*theme_set
+    };
+
+    for (name, theme) in &theme_set.themes.themes {
+        register(name, theme.clone());
+    }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_unregister_theme(
+    theme_name: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let Some(theme_name) = str_arg(theme_name, "Invalid theme name", error) else {
+        return false;
+    };
+
+    let mut themes = registry().write().unwrap_or_else(|e| e.into_inner());
+    if themes.remove(theme_name).is_none() {
+        set_error(error, format!("Theme '{}' is not registered", theme_name));
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_builder::{syntect_theme_add_rule, syntect_theme_new};
+    use crate::{
+        syntect_create_file, syntect_create_lines, syntect_free_file, syntect_free_lines,
+        syntect_free_string, syntect_free_theme, syntect_highlight_text_line,
+    };
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    #[test]
+    fn test_register_theme() {
+        let mut error: *const c_char = ptr::null();
+        let name = CString::new("registry-test-theme").unwrap();
+        let theme = syntect_theme_new(name.as_ptr(), &mut error);
+        let scope = CString::new("storage").unwrap();
+        let foreground = CString::new("#123456").unwrap();
+        assert!(syntect_theme_add_rule(
+            theme,
+            scope.as_ptr(),
+            foreground.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            &mut error
+        ));
+
+        assert!(syntect_register_theme(name.as_ptr(), theme, &mut error));
+        syntect_free_theme(theme);
+
+        let lines = syntect_create_lines(name.as_ptr(), &mut error);
+        assert!(!lines.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+        let path = CString::new("test/hello_world.c").unwrap();
+        let file = syntect_create_file(path.as_ptr(), name.as_ptr(), &mut error);
+        assert!(!file.is_null());
+
+        // Highlighters created before unregistering keep working.
+        assert!(syntect_unregister_theme(name.as_ptr(), &mut error));
+        let line = CString::new("fn main() {}\n").unwrap();
+        let highlighted = syntect_highlight_text_line(lines, line.as_ptr(), &mut error);
+        let highlighted_str = unsafe { CStr::from_ptr(highlighted).to_str().unwrap() };
+        assert!(
+            highlighted_str.contains("\x1b[38;2;18;52;86mfn"),
+            "{:?}",
+            highlighted_str
+        );
+        syntect_free_string(highlighted as *mut c_char);
+
+        assert!(syntect_create_lines(name.as_ptr(), &mut error).is_null());
+        syntect_free_string(error as *mut c_char);
+
+        syntect_free_file(file);
+        syntect_free_lines(lines);
+    }
+}
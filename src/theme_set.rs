@@ -0,0 +1,171 @@
+//! Functions that build and edit theme sets.
+
+use crate::{load_theme_set_from_folder, set_error, str_arg, SyntectTheme, SyntectThemeSet};
+use libc::c_char;
+use std::ptr;
+use syntect::highlighting::ThemeSet;
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_set_new(_error: *mut *const c_char) -> *mut SyntectThemeSet {
+    Box::into_raw(Box::new(SyntectThemeSet {
+        themes: ThemeSet::new(),
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_set_add_theme(
+    theme_set: *mut SyntectThemeSet,
+    theme_name: *const c_char,
+    theme: *const SyntectTheme,
+    error: *mut *const c_char,
+) -> bool {
+    let theme_set = unsafe {
+        assert!(!theme_set.is_null());
+        &mut *theme_set
+    };
+    let theme = unsafe {
+        assert!(!theme.is_null());
+        &(*theme).theme
+    };
+    let Some(theme_name) = str_arg(theme_name, "Invalid theme name", error) else {
+        return false;
+    };
+
+    theme_set
+        .themes
+        .themes
+        .insert(theme_name.to_string(), theme.clone());
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_set_remove_theme(
+    theme_set: *mut SyntectThemeSet,
+    theme_name: *const c_char,
+    error: *mut *const c_char,
+) -> bool {
+    let theme_set = unsafe {
+        assert!(!theme_set.is_null());
+        &mut *theme_set
+    };
+    let Some(theme_name) = str_arg(theme_name, "Invalid theme name", error) else {
+        return false;
+    };
+
+    if theme_set.themes.themes.remove(theme_name).is_none() {
+        set_error(error, format!("Theme '{}' not found", theme_name));
+        return false;
+    }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_theme_set_merge(
+    theme_set: *mut SyntectThemeSet,
+    other: *const SyntectThemeSet,
+    _error: *mut *const c_char,
+) -> bool {
+    let theme_set = unsafe {
+        assert!(!theme_set.is_null());
+        &mut *theme_set
+    };
+    let other = unsafe {
+        assert!(!other.is_null());
+        &*other
+    };
+
+    for (name, theme) in &other.themes.themes {
+        theme_set.themes.themes.insert(name.clone(), theme.clone());
+    }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_load_theme_set_from_folders(
+    folders: *const *const c_char,
+    count: usize,
+    enable_caching: bool,
+    error: *mut *const c_char,
+) -> *mut SyntectThemeSet {
+    assert!(count == 0 || !folders.is_null());
+
+    let mut themes = ThemeSet::new();
+    for i in 0..count {
+        let folder = unsafe { *folders.add(i) };
+        let Some(folder) = str_arg(folder, "Invalid folder path", error) else {
+            return ptr::null_mut();
+        };
+        match load_theme_set_from_folder(folder, enable_caching) {
+            Ok(theme_set) => themes.themes.extend(theme_set.themes),
+            Err(err) => {
+                set_error(
+                    error,
+                    format!("Failed to load themes from folder {}: {}", folder, err),
+                );
+                return ptr::null_mut();
+            }
+        }
+    }
+
+    Box::into_raw(Box::new(SyntectThemeSet { themes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme_builder::syntect_theme_new;
+    use crate::{syntect_free_string, syntect_free_theme, syntect_free_theme_set};
+    use std::ffi::CString;
+
+    #[test]
+    fn test_edit_theme_set() {
+        let mut error: *const c_char = ptr::null();
+        let theme_set = syntect_theme_set_new(&mut error);
+        let theme = syntect_theme_new(ptr::null(), &mut error);
+        let name = CString::new("custom").unwrap();
+
+        assert!(syntect_theme_set_add_theme(
+            theme_set,
+            name.as_ptr(),
+            theme,
+            &mut error
+        ));
+        assert!(unsafe { (*theme_set).themes.themes.contains_key("custom") });
+
+        let folder = CString::new("test/themes").unwrap();
+        let folders = [folder.as_ptr(), folder.as_ptr()];
+        let loaded = syntect_load_theme_set_from_folders(folders.as_ptr(), 2, false, &mut error);
+        assert!(!loaded.is_null(), "Failed to load theme set");
+        assert!(syntect_theme_set_merge(theme_set, loaded, &mut error));
+        assert!(unsafe { (*theme_set).themes.themes.contains_key("base16-ocean") });
+
+        assert!(syntect_theme_set_remove_theme(
+            theme_set,
+            name.as_ptr(),
+            &mut error
+        ));
+        assert!(!syntect_theme_set_remove_theme(
+            theme_set,
+            name.as_ptr(),
+            &mut error
+        ));
+        assert!(!error.is_null());
+        syntect_free_string(error as *mut c_char);
+
+        syntect_free_theme_set(loaded);
+        syntect_free_theme_set(theme_set);
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_load_from_missing_folder() {
+        let mut error: *const c_char = ptr::null();
+        let folder = CString::new("test/no-such-folder").unwrap();
+        let folders = [folder.as_ptr()];
+
+        let loaded = syntect_load_theme_set_from_folders(folders.as_ptr(), 1, false, &mut error);
+        assert!(loaded.is_null());
+        assert!(!error.is_null());
+        syntect_free_string(error as *mut c_char);
+    }
+}
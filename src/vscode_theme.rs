@@ -0,0 +1,252 @@
+//! Converter for VS Code JSON color themes.
+
+use crate::color::parse_css_color;
+use crate::sublime_scheme::parse_json_with_comments;
+use crate::theme_builder::{format_color, parse_theme_item, set_theme_setting};
+use crate::{load_theme_with, set_error, str_arg, SyntectTheme};
+use libc::c_char;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr;
+use syntect::highlighting::{Theme, ThemeSet};
+
+/// VS Code workbench color keys and the syntect theme settings they map to.
+const EDITOR_COLORS: [(&str, &str); 17] = [
+    ("editor.background", "background"),
+    ("editor.foreground", "foreground"),
+    ("editorCursor.foreground", "caret"),
+    ("editor.lineHighlightBackground", "lineHighlight"),
+    ("editor.selectionBackground", "selection"),
+    ("editor.selectionForeground", "selectionForeground"),
+    ("editor.inactiveSelectionBackground", "inactiveSelection"),
+    ("editor.findMatchHighlightBackground", "findHighlight"),
+    ("editor.wordHighlightBackground", "highlight"),
+    ("editorGutter.background", "gutter"),
+    ("editorLineNumber.foreground", "gutterForeground"),
+    ("editorIndentGuide.background", "guide"),
+    ("editorIndentGuide.activeBackground", "activeGuide"),
+    ("editorBracketMatch.background", "bracketsBackground"),
+    ("editorError.foreground", "misspelling"),
+    ("editorOverviewRuler.border", "minimapBorder"),
+    ("focusBorder", "accent"),
+];
+
+/// Loads a VS Code color theme file, following its `include` chain.
+pub(crate) fn load_vscode_theme(path: &Path) -> Result<Theme, String> {
+    let mut theme = Theme::default();
+    apply_vscode_theme(&mut theme, path, 0)?;
+    if theme.name.is_none() {
+        theme.name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(|stem| stem.to_string());
+    }
+    Ok(theme)
+}
+
+fn apply_vscode_theme(theme: &mut Theme, path: &Path, depth: usize) -> Result<(), String> {
+    if depth > 16 {
+        return Err(format!(
+            "Theme include chain too deep at {}",
+            path.display()
+        ));
+    }
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error loading theme {}: {}", path.display(), e))?;
+    let json = parse_json_with_comments(&text)?;
+    let obj = json
+        .as_object()
+        .ok_or_else(|| "Color theme is not a JSON object".to_string())?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    // Included themes are applied first so that this file overrides them.
+    if let Some(include) = obj.get("include").and_then(Value::as_str) {
+        apply_vscode_theme(theme, &dir.join(include), depth + 1)?;
+    }
+
+    if let Some(name) = obj.get("name").and_then(Value::as_str) {
+        theme.name = Some(name.to_string());
+    }
+    if let Some(author) = obj.get("author").and_then(Value::as_str) {
+        theme.author = Some(author.to_string());
+    }
+
+    let no_variables = HashMap::new();
+    let colors = obj.get("colors").and_then(Value::as_object);
+    for (vscode_key, setting) in EDITOR_COLORS {
+        let color = colors
+            .and_then(|colors| colors.get(vscode_key))
+            .and_then(Value::as_str)
+            .and_then(|value| parse_css_color(value, &no_variables).ok());
+        if let Some(color) = color {
+            set_theme_setting(&mut theme.settings, setting, Some(&format_color(color)))?;
+        }
+    }
+
+    match obj.get("tokenColors") {
+        Some(Value::Array(rules)) => {
+            for rule in rules {
+                apply_token_color(theme, rule, colors.is_some());
+            }
+        }
+        // `tokenColors` may also point at a `.tmTheme` file holding the rules.
+        Some(Value::String(tm_theme)) => {
+            let tm_theme = ThemeSet::get_theme(dir.join(tm_theme))
+                .map_err(|e| format!("Error loading theme: {}", e))?;
+            theme.scopes.extend(tm_theme.scopes);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Adds one `tokenColors` entry. Entries that syntect cannot represent are skipped, the way
+/// syntect skips unparsable rules in `.tmTheme` files.
+fn apply_token_color(theme: &mut Theme, rule: &Value, has_editor_colors: bool) {
+    let Some(settings) = rule.get("settings").and_then(Value::as_object) else {
+        return;
+    };
+    let no_variables = HashMap::new();
+    let color = |key: &str| {
+        settings
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(|value| parse_css_color(value, &no_variables).ok())
+            .map(format_color)
+    };
+    let foreground = color("foreground");
+    let background = color("background");
+
+    let scope = match rule.get("scope") {
+        Some(Value::String(scope)) => scope.clone(),
+        Some(Value::Array(scopes)) => scopes
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => {
+            // A rule without a scope holds the global colors, as in a `.tmTheme` file.
+            if !has_editor_colors {
+                let _ = set_theme_setting(&mut theme.settings, "foreground", foreground.as_deref());
+                let _ = set_theme_setting(&mut theme.settings, "background", background.as_deref());
+            }
+            return;
+        }
+    };
+    if scope.trim().is_empty() {
+        return;
+    }
+
+    let font_style = settings.get("fontStyle").and_then(Value::as_str).map(|s| {
+        s.split_whitespace()
+            .filter(|word| matches!(*word, "bold" | "italic" | "underline"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    });
+
+    if let Ok(item) = parse_theme_item(
+        &scope,
+        foreground.as_deref(),
+        background.as_deref(),
+        font_style.as_deref(),
+    ) {
+        theme.scopes.push(item);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn syntect_load_vscode_theme(
+    theme_path: *const c_char,
+    enable_caching: bool,
+    error: *mut *const c_char,
+) -> *mut SyntectTheme {
+    let Some(theme_path) = str_arg(theme_path, "Invalid theme path", error) else {
+        return ptr::null_mut();
+    };
+
+    match load_theme_with(Path::new(theme_path), enable_caching, load_vscode_theme) {
+        Ok(theme) => Box::into_raw(Box::new(SyntectTheme { theme })),
+        Err(err) => {
+            set_error(error, err);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntect_free_theme;
+    use std::ffi::{CStr, CString};
+    use syntect::highlighting::{Color, FontStyle};
+
+    #[test]
+    fn test_load_vscode_theme() {
+        let theme_path = CString::new("test/themes/sample-color-theme.json").unwrap();
+        let mut error: *const c_char = ptr::null();
+
+        let theme = syntect_load_vscode_theme(theme_path.as_ptr(), false, &mut error);
+        assert!(!theme.is_null(), "Unexpected error: {:?}", unsafe {
+            CStr::from_ptr(error).to_str().unwrap()
+        });
+
+        let loaded = unsafe { &(*theme).theme };
+        assert_eq!(loaded.name.as_deref(), Some("Sample VS Code"));
+        assert_eq!(
+            loaded.settings.background,
+            Some(Color {
+                r: 0x1e,
+                g: 0x1e,
+                b: 0x1e,
+                a: 0xff
+            })
+        );
+        assert!(loaded.settings.gutter_foreground.is_some());
+        let comment = loaded
+            .scopes
+            .iter()
+            .find(|item| item.style.font_style == Some(FontStyle::ITALIC))
+            .unwrap();
+        assert_eq!(comment.scope.selectors.len(), 2);
+
+        syntect_free_theme(theme);
+    }
+
+    #[test]
+    fn test_vscode_theme_include() {
+        let dir = std::env::temp_dir().join("syntect-c-test-vscode-include");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.json"),
+            r##"{"colors": {"editor.background": "#000000", "editor.foreground": "#ffffff"}}"##,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("child.json"),
+            r##"{
+                "include": "./base.json",
+                "colors": {"editor.foreground": "#eeeeee"},
+                "tokenColors": [{"scope": "string", "settings": {"foreground": "#00ff00"}}]
+            }"##,
+        )
+        .unwrap();
+
+        let theme = load_vscode_theme(&dir.join("child.json")).unwrap();
+        assert_eq!(theme.name.as_deref(), Some("child"));
+        assert_eq!(theme.settings.background, Some(Color::BLACK));
+        assert_eq!(
+            theme.settings.foreground,
+            Some(Color {
+                r: 0xee,
+                g: 0xee,
+                b: 0xee,
+                a: 0xff
+            })
+        );
+        assert_eq!(theme.scopes.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
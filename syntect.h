@@ -1,6 +1,10 @@
 #ifndef SYNTECT_H
 #define SYNTECT_H
 
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
 #ifdef __cplusplus
 extern "C"
 {
@@ -34,13 +38,193 @@ extern "C"
    */
   typedef struct SyntectTheme SyntectTheme;
 
+  /**
+   * @brief Output options for the file and line highlighters.
+   *
+   * Created with syntect_options_new and applied with syntect_file_set_options or
+   * syntect_lines_set_options.
+   */
+  typedef struct SyntectOptions SyntectOptions;
+
+  /**
+   * @brief The output formats a highlighter can render.
+   */
+  typedef enum SyntectOutputFormat
+  {
+    /** 24-bit color terminal escape sequences. This is the default. The document ends with a reset sequence. */
+    SYNTECT_OUTPUT_ANSI = 0,
+    /** HTML spans with inline styles. */
+    SYNTECT_OUTPUT_HTML = 1,
+    /** 24-bit color terminal escape sequences with the theme's bold, italic and underline styles. Font styles are turned off at the end of each line, and the document ends with a reset sequence. */
+    SYNTECT_OUTPUT_ANSI_STYLED = 2,
+    /** LaTeX for a monospaced listing, colored with xcolor. The colors of the theme are referred to by the names defined by syntect_theme_to_latex_preamble. */
+    SYNTECT_OUTPUT_LATEX = 3,
+    /** An RTF document in Courier New, for copying as rich text. Its color table holds the theme's colors, the gutter and line highlight colors and the colors set in the options; other colors are drawn as the closest one in the table. */
+    SYNTECT_OUTPUT_RTF = 4,
+    /** An SVG image with a <text> element per line, laid out as set with syntect_options_set_svg_layout. The root element needs the size of the image, so only the functions that highlight a whole text at once, such as syntect_highlight_buffer, write this format; highlighting lines one by one and syntect_file_begin_document and the like fail for it. Control characters are written as Unicode control pictures, since XML does not allow them. */
+    SYNTECT_OUTPUT_SVG = 5,
+    /** Pango markup, one <span> per token. The document is wrapped in a monospace span with the theme colors. Control characters are written as Unicode control pictures, since the markup parser rejects them. */
+    SYNTECT_OUTPUT_PANGO = 6,
+    /** BBCode with [color], [b], [i] and [u] tags. Backgrounds are left out. BBCode has no escape of its own, so brackets in the text are written as the HTML character references "&#91;" and "&#93;", and "&" as "&amp;"; this suits forums that decode character references in posts. */
+    SYNTECT_OUTPUT_BBCODE = 7,
+  } SyntectOutputFormat;
+
+  /**
+   * @brief How control characters in the highlighted text are written out.
+   */
+  typedef enum SyntectControlChars
+  {
+    /** Control characters are passed through unchanged. This is the default. */
+    SYNTECT_CONTROL_CHARS_KEEP = 0,
+    /** Control characters are written in caret notation, such as "^[" for ESC. */
+    SYNTECT_CONTROL_CHARS_CARET = 1,
+    /** Control characters are written as Unicode control pictures, such as U+241B for ESC. */
+    SYNTECT_CONTROL_CHARS_PICTURES = 2,
+  } SyntectControlChars;
+
+  /**
+   * @brief What happens to the line endings of highlighted lines.
+   */
+  typedef enum SyntectLineEndings
+  {
+    /** Each line is written out with the ending it came with, "\n" or "\r\n". This is the default. */
+    SYNTECT_LINE_ENDINGS_PRESERVE = 0,
+    /** "\r\n" endings are written out as "\n". */
+    SYNTECT_LINE_ENDINGS_NORMALIZE = 1,
+    /** Lines are passed without endings and written out without them, using the syntax definitions made for lines without newlines. The functions that highlight a whole text at once, such as syntect_highlight_buffer, still separate its lines with "\n". */
+    SYNTECT_LINE_ENDINGS_STRIP = 2,
+  } SyntectLineEndings;
+
+  /**
+   * @brief A theme rule whose foreground does not contrast enough with its background.
+   *
+   * Returned by syntect_theme_find_low_contrast_rules.
+   */
+  typedef struct SyntectContrastIssue
+  {
+    /** Index of the rule in the theme. */
+    size_t rule_index;
+    /** The scope selector of the rule. */
+    char *scope;
+    /** The WCAG contrast ratio of the rule's foreground against its background, from 1 to 21. */
+    double ratio;
+  } SyntectContrastIssue;
+
+  /**
+   * @brief A fenced or indented code block of a Markdown document.
+   *
+   * Returned by syntect_markdown_find_code_blocks. Offsets are in bytes from the start of the document.
+   */
+  typedef struct SyntectCodeBlock
+  {
+    /** Offset of the start of the block, fences included. */
+    size_t start;
+    /** Offset of the end of the block, fences included. */
+    size_t end;
+    /** Offset of the start of the code. */
+    size_t code_start;
+    /** Offset of the end of the code. */
+    size_t code_end;
+    /** The language named by the info string of a fenced block, such as "rust" for "```rust,ignore", or NULL if there is none or it contains a NUL character. */
+    char *language;
+    /** The name of the syntax the block is highlighted with, "Plain Text" when the language is unknown. */
+    char *syntax_name;
+  } SyntectCodeBlock;
+
+  /**
+   * @brief An RGBA color.
+   */
+  typedef struct SyntectColor
+  {
+    uint8_t r;
+    uint8_t g;
+    uint8_t b;
+    uint8_t a;
+  } SyntectColor;
+
+  /**
+   * @brief Font style flags of a SyntectSpan, combined with bitwise OR.
+   */
+  typedef enum SyntectFontStyle
+  {
+    SYNTECT_FONT_STYLE_BOLD = 1,
+    SYNTECT_FONT_STYLE_UNDERLINE = 2,
+    SYNTECT_FONT_STYLE_ITALIC = 4
+  } SyntectFontStyle;
+
+  /**
+   * @brief A piece of a highlighted line drawn in one style.
+   *
+   * Returned by syntect_highlight_text_line_spans and syntect_highlight_file_line_spans.
+   * Offsets are in bytes from the start of the line; the line ending is not included.
+   */
+  typedef struct SyntectSpan
+  {
+    /** Offset of the start of the span. */
+    size_t start;
+    /** Offset of the end of the span. */
+    size_t end;
+    /** The text of the span, end - start bytes followed by a NUL. */
+    char *text;
+    /** The foreground color, with search match emphasis applied. */
+    SyntectColor foreground;
+    /** The background color, with search match emphasis applied. */
+    SyntectColor background;
+    /** A combination of SyntectFontStyle flags. */
+    uint8_t font_style;
+    /** Whether the span is part of a search match. */
+    bool matched;
+  } SyntectSpan;
+
+  /**
+   * @brief The output of a document being rendered by a SyntectRendererCallbacks.
+   *
+   * Only valid during the callback it is passed to. Written to with syntect_output_write.
+   */
+  typedef struct SyntectOutput SyntectOutput;
+
+  /**
+   * @brief The style of a token passed to SyntectRendererCallbacks.token.
+   */
+  typedef struct SyntectTokenStyle
+  {
+    /** The foreground color, drawn over the background so that it is opaque. */
+    SyntectColor foreground;
+    /** The background color. */
+    SyntectColor background;
+    /** A combination of SyntectFontStyle flags. */
+    uint8_t font_style;
+  } SyntectTokenStyle;
+
+  /**
+   * @brief A renderer implemented by the caller, for output formats the library does not provide.
+   *
+   * Set with syntect_options_set_renderer. The library calls begin_document and end_document
+   * where it frames a document, and begin_line, token and end_line for each row of a line (a
+   * wrapped line has several rows, and line numbers come as tokens). The callbacks write their output with syntect_output_write, and the
+   * highlighting functions return what was written. Every callback may be NULL.
+   */
+  typedef struct SyntectRendererCallbacks
+  {
+    /** Passed back to every callback. */
+    void *user_data;
+    void (*begin_document)(void *user_data, SyntectOutput *out);
+    /** Called before each row with the number of its line. */
+    void (*begin_line)(void *user_data, SyntectOutput *out, size_t line_number);
+    /** Called with len bytes of UTF-8 text, not NUL-terminated. The text may be empty. */
+    void (*token)(void *user_data, SyntectOutput *out, const SyntectTokenStyle *style, const char *text, size_t len);
+    /** Called after each row with the line ending to write, which may be empty. */
+    void (*end_line)(void *user_data, SyntectOutput *out, const char *line_ending, size_t len);
+    void (*end_document)(void *user_data, SyntectOutput *out);
+  } SyntectRendererCallbacks;
+
   /**
    * @brief Creates a SyntectFile for highlighting a file.
    *
    * This function initializes a SyntectFile, which can be used to highlight the content of a file.
    *
    * @param path The path to the file to be highlighted.
-   * @param theme_name The name of the theme to be used for highlighting.
+   * @param theme_name The name of the theme to be used for highlighting. Themes registered with syntect_register_theme are looked up before the default themes.
    * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
    * @return Pointer to the created SyntectFile, or NULL if an error occurs.
    */
@@ -62,13 +246,67 @@ extern "C"
    * @brief Highlights a line from the file.
    *
    * This function reads and highlights a single line from the file associated with the given SyntectFile.
+   * The file is read as UTF-8, or UTF-16 when it starts with a byte order mark, unless another
+   * encoding was set with syntect_options_set_encoding. Invalid byte sequences are replaced with
+   * U+FFFD. A file that looks binary (it has a NUL byte in its first 8 KiB and is not UTF-16) is
+   * refused: the first call returns NULL and sets the error.
    *
    * @param wrapper Pointer to the SyntectFile.
    * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
-   * @return The highlighted line as a C string, or NULL if an error occurs. The returned string should be freed using syntect_free_string.
+   * @return The highlighted line as a C string, or NULL at the end of the file or if an error occurs. The returned string should be freed using syntect_free_string.
    */
   const char *syntect_highlight_file_line(SyntectFile *wrapper, const char **error);
 
+  /**
+   * @brief Highlights a line from the file, returning its length along with it.
+   *
+   * Works like syntect_highlight_file_line, but the output may contain NUL characters from the
+   * file.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param out_len Pointer to store the length of the returned buffer in bytes, not counting its terminating NUL.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The highlighted line, or NULL at the end of the file or if an error occurs. The returned buffer should be freed using syntect_free_buffer.
+   */
+  const char *syntect_highlight_file_line_len(SyntectFile *wrapper, size_t *out_len, const char **error);
+
+  /**
+   * @brief Highlights a line from the file into styled spans instead of rendered text.
+   *
+   * Output options other than the encoding, line endings and match colors do not apply.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param count Pointer to store the number of spans.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return An array of count spans, non-NULL even for an empty line, or NULL at the end of the file or if an error occurs. The array should be freed using syntect_free_spans.
+   */
+  SyntectSpan *syntect_highlight_file_line_spans(SyntectFile *wrapper, size_t *count, const char **error);
+
+  /**
+   * @brief Emphasizes byte ranges of the next line read from the file.
+   *
+   * The ranges apply to the next highlighted line only, in addition to the matches of the
+   * pattern set with syntect_file_set_match_regex. They may overlap and are widened to whole
+   * characters. Matched text is drawn in the colors set with syntect_options_set_match_colors.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param ranges count pairs of start and end offsets, in bytes from the start of the line. May be NULL if count is 0.
+   * @param count The number of ranges.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the ranges are valid, false otherwise.
+   */
+  bool syntect_file_set_matches(SyntectFile *wrapper, const size_t *ranges, size_t count, const char **error);
+
+  /**
+   * @brief Emphasizes the matches of a regular expression in every line of the file.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param pattern An Oniguruma regular expression, or NULL to stop emphasizing matches.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the pattern is valid, false otherwise.
+   */
+  bool syntect_file_set_match_regex(SyntectFile *wrapper, const char *pattern, const char **error);
+
   /**
    * @brief Creates a SyntectLines for highlighting lines of text with a specified theme.
    *
@@ -94,7 +332,7 @@ extern "C"
    *
    * This function initializes a SyntectLines, which can be used to highlight individual lines of text.
    *
-   * @param theme_name The name of the theme to be used for highlighting.
+   * @param theme_name The name of the theme to be used for highlighting. Themes registered with syntect_register_theme are looked up before the default themes.
    * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
    * @return Pointer to the created SyntectLines, or NULL if an error occurs.
    */
@@ -112,6 +350,141 @@ extern "C"
    */
   const char *syntect_highlight_text_line(SyntectLines *wrapper, const char *line, const char **error);
 
+  /**
+   * @brief Highlights a line of text given as a buffer with an explicit length.
+   *
+   * Works like syntect_highlight_text_line, but the input does not need to be NUL-terminated and
+   * may contain NUL characters. It must be valid UTF-8.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param data The line of text to be highlighted. May be NULL if len is 0.
+   * @param len The length of the line in bytes.
+   * @param out_len Pointer to store the length of the returned buffer in bytes, not counting its terminating NUL.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The highlighted line, or NULL if an error occurs. The returned buffer should be freed using syntect_free_buffer.
+   */
+  const char *syntect_highlight_text_line_len(SyntectLines *wrapper, const char *data, size_t len, size_t *out_len, const char **error);
+
+  /**
+   * @brief Highlights a whole buffer of text as one document.
+   *
+   * The text is split into lines, each highlighted in turn, and the output is framed by what
+   * syntect_lines_begin_document and syntect_lines_end_document return. Highlighting continues
+   * from the state left by previous calls on the same SyntectLines.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param data The UTF-8 text to be highlighted. May be NULL if len is 0.
+   * @param len The length of the text in bytes.
+   * @param out_len Pointer to store the length of the returned buffer in bytes, not counting its terminating NUL.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The highlighted document, or NULL if an error occurs. The returned buffer should be freed using syntect_free_buffer.
+   */
+  const char *syntect_highlight_buffer(SyntectLines *wrapper, const char *data, size_t len, size_t *out_len, const char **error);
+
+  /**
+   * @brief Highlights a line of text into styled spans instead of rendered text.
+   *
+   * Output options other than the line endings and match colors do not apply.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param data The line of text to be highlighted. May be NULL if len is 0.
+   * @param len The length of the line in bytes.
+   * @param count Pointer to store the number of spans.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return An array of count spans, non-NULL even for an empty line, or NULL if an error occurs. The array should be freed using syntect_free_spans.
+   */
+  SyntectSpan *syntect_highlight_text_line_spans(SyntectLines *wrapper, const char *data, size_t len, size_t *count, const char **error);
+
+  /**
+   * @brief Frees an array returned by syntect_highlight_text_line_spans or syntect_highlight_file_line_spans.
+   *
+   * @param spans Pointer to the array to be freed.
+   * @param count The number of spans in the array.
+   */
+  void syntect_free_spans(SyntectSpan *spans, size_t count);
+
+  /**
+   * @brief Emphasizes byte ranges of the next line of text.
+   *
+   * Works like syntect_file_set_matches.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param ranges count pairs of start and end offsets, in bytes from the start of the line. May be NULL if count is 0.
+   * @param count The number of ranges.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the ranges are valid, false otherwise.
+   */
+  bool syntect_lines_set_matches(SyntectLines *wrapper, const size_t *ranges, size_t count, const char **error);
+
+  /**
+   * @brief Emphasizes the matches of a regular expression in every line of text.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param pattern An Oniguruma regular expression, or NULL to stop emphasizing matches.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the pattern is valid, false otherwise.
+   */
+  bool syntect_lines_set_match_regex(SyntectLines *wrapper, const char *pattern, const char **error);
+
+  /**
+   * @brief Finds the code blocks of a Markdown document.
+   *
+   * Both fenced (``` and ~~~) and indented code blocks are found. Indented code is not
+   * recognized inside list items.
+   *
+   * @param data The UTF-8 Markdown text. May be NULL if len is 0.
+   * @param len The length of the text in bytes.
+   * @param count Pointer to store the number of blocks found.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return An array of count blocks, or NULL if there are none or an error occurs. The array should be freed using syntect_free_code_blocks.
+   */
+  SyntectCodeBlock *syntect_markdown_find_code_blocks(const char *data, size_t len, size_t *count, const char **error);
+
+  /**
+   * @brief Frees an array returned by syntect_markdown_find_code_blocks.
+   *
+   * @param blocks Pointer to the array to be freed.
+   * @param count The number of blocks in the array.
+   */
+  void syntect_free_code_blocks(SyntectCodeBlock *blocks, size_t count);
+
+  /**
+   * @brief Highlights the code blocks of a Markdown document.
+   *
+   * Each code block, fences included, is replaced by its highlighted code, framed by the output
+   * format's document prefix and suffix (a <pre> element for HTML). The rest of the document is
+   * copied unchanged. The language of a fenced block is looked up by the first word of its info
+   * string, as a syntax name or file extension.
+   *
+   * @param data The UTF-8 Markdown text. May be NULL if len is 0.
+   * @param len The length of the text in bytes.
+   * @param theme_name The name of the theme to be used for highlighting.
+   * @param options Pointer to the output options, or NULL for the defaults.
+   * @param out_len Pointer to store the length of the returned buffer in bytes, not counting its terminating NUL.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The highlighted document, or NULL if an error occurs. The returned buffer should be freed using syntect_free_buffer.
+   */
+  const char *syntect_markdown_highlight(const char *data, size_t len, const char *theme_name, const SyntectOptions *options, size_t *out_len, const char **error);
+
+  /**
+   * @brief Highlights a unified diff.
+   *
+   * File and hunk headers are highlighted with the Diff syntax. The code in each hunk is
+   * highlighted with the syntax of the file named by the "+++" header (or the "---" header for
+   * deleted files), following the old version through removed and context lines and the new
+   * version through added and context lines. Added and removed lines are drawn on the
+   * backgrounds set with syntect_options_set_diff_backgrounds.
+   *
+   * @param data The UTF-8 diff text. May be NULL if len is 0.
+   * @param len The length of the text in bytes.
+   * @param theme_name The name of the theme to be used for highlighting.
+   * @param options Pointer to the output options, or NULL for the defaults.
+   * @param out_len Pointer to store the length of the returned buffer in bytes, not counting its terminating NUL.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The highlighted diff, or NULL if an error occurs. The returned buffer should be freed using syntect_free_buffer.
+   */
+  const char *syntect_diff_highlight(const char *data, size_t len, const char *theme_name, const SyntectOptions *options, size_t *out_len, const char **error);
+
   /**
    * @brief Frees the SyntectLines.
    *
@@ -121,6 +494,324 @@ extern "C"
    */
   void syntect_free_lines(SyntectLines *wrapper);
 
+  /**
+   * @brief Creates a set of output options with the default values.
+   *
+   * By default the output is ANSI, without line numbers or a highlighted line.
+   *
+   * @return Pointer to the created SyntectOptions. This should be freed using syntect_options_free.
+   */
+  SyntectOptions *syntect_options_new(void);
+
+  /**
+   * @brief Frees the SyntectOptions.
+   *
+   * @param options Pointer to the SyntectOptions to be freed.
+   */
+  void syntect_options_free(SyntectOptions *options);
+
+  /**
+   * @brief Sets the output format.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param format The output format.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if the format is unknown.
+   */
+  bool syntect_options_set_format(SyntectOptions *options, SyntectOutputFormat format, const char **error);
+
+  /**
+   * @brief Renders output with callbacks instead of one of the built-in formats.
+   *
+   * The callbacks are copied; user_data must stay valid while highlighters use the options.
+   * Setting a format with syntect_options_set_format replaces the callbacks.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param callbacks Pointer to the callbacks, or NULL to go back to SYNTECT_OUTPUT_ANSI.
+   */
+  void syntect_options_set_renderer(SyntectOptions *options, const SyntectRendererCallbacks *callbacks);
+
+  /**
+   * @brief Appends text to the output of a document from a SyntectRendererCallbacks callback.
+   *
+   * @param out The output passed to the callback.
+   * @param data The UTF-8 text to write. May be NULL if len is 0.
+   * @param len The length of the text in bytes.
+   * @return true on success, false if the text is not valid UTF-8.
+   */
+  bool syntect_output_write(SyntectOutput *out, const char *data, size_t len);
+
+  /**
+   * @brief Leaves font styles out of SYNTECT_OUTPUT_ANSI_STYLED output.
+   *
+   * For terminals that render some styles poorly, such as italic drawn as inverse video. By
+   * default all styles are written.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param styles A combination of SyntectFontStyle flags to suppress, or 0 for none.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if styles contains unknown flags.
+   */
+  bool syntect_options_set_suppressed_font_styles(SyntectOptions *options, unsigned int styles, const char **error);
+
+  /**
+   * @brief Sets the layout of SYNTECT_OUTPUT_SVG output.
+   *
+   * Text is placed assuming a monospace font whose characters are 0.6 em wide. The defaults are
+   * a font size of 14, a line height of 1.4 and a padding of 16.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param font_size The font size in pixels. Must be positive.
+   * @param line_height The distance between lines as a multiple of the font size. Must be positive.
+   * @param padding The space between the code and the edges of the image in pixels. Must not be negative.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if a value is out of range.
+   */
+  bool syntect_options_set_svg_layout(SyntectOptions *options, double font_size, double line_height, double padding, const char **error);
+
+  /**
+   * @brief Enables or disables line numbers.
+   *
+   * Line numbers are drawn in a gutter before each line, colored with the theme's "gutter" and
+   * "gutterForeground" settings. Without them the gutter takes the theme background and a
+   * dimmed foreground.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param enabled Whether to draw line numbers.
+   */
+  void syntect_options_set_line_numbers(SyntectOptions *options, bool enabled);
+
+  /**
+   * @brief Sets the minimum width of line numbers, which are right-aligned. The default is 4.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param width The width in characters.
+   */
+  void syntect_options_set_line_number_width(SyntectOptions *options, size_t width);
+
+  /**
+   * @brief Sets the number of the first line. The default is 1.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param line_number The number of the first highlighted line.
+   */
+  void syntect_options_set_first_line_number(SyntectOptions *options, size_t line_number);
+
+  /**
+   * @brief Sets the separator drawn between the line numbers and the code. The default is "│".
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param separator The separator, or NULL for none.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_options_set_gutter_separator(SyntectOptions *options, const char *separator, const char **error);
+
+  /**
+   * @brief Sets the current line, drawn with the theme's "lineHighlight" background.
+   *
+   * Tokens that have a background of their own keep it.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param line_number The number of the line to highlight, or 0 for none.
+   */
+  void syntect_options_set_highlighted_line(SyntectOptions *options, size_t line_number);
+
+  /**
+   * @brief Sets the tab width used to expand tabs into spaces.
+   *
+   * Tabs are expanded to the next multiple of the width, counted from the start of the code.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param tab_width The distance between tab stops, or 0 to leave tabs unchanged (the default).
+   */
+  void syntect_options_set_tab_width(SyntectOptions *options, size_t tab_width);
+
+  /**
+   * @brief Sets how control characters are written out.
+   *
+   * Making control characters visible stops escape sequences embedded in the source from
+   * reaching the terminal. Tabs and line endings are not affected. Characters without a caret
+   * or picture form (C1 controls) are written as their hexadecimal code, such as "<85>". The
+   * replacement keeps the style of the token it appears in.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param mode How to write control characters.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if the mode is unknown.
+   */
+  bool syntect_options_set_control_chars(SyntectOptions *options, SyntectControlChars mode, const char **error);
+
+  /**
+   * @brief Sets the width at which lines are soft-wrapped.
+   *
+   * A line wider than the width is split into several rows. Rows end like the line they belong
+   * to: with its own ending under SYNTECT_LINE_ENDINGS_PRESERVE, and with "\n" under
+   * SYNTECT_LINE_ENDINGS_NORMALIZE. Under SYNTECT_LINE_ENDINGS_STRIP, and for a last line
+   * without an ending, rows are still separated by "\n"; only the last row goes without. Widths
+   * are counted in display columns, with East Asian wide characters taking two columns, and do
+   * not include the gutter. A token that is split carries its style over to the next row, and
+   * continuation rows get an empty gutter. Tabs should be expanded with
+   * syntect_options_set_tab_width for their width to be counted.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param width The number of columns per row, or 0 to disable wrapping (the default).
+   */
+  void syntect_options_set_wrap_width(SyntectOptions *options, size_t width);
+
+  /**
+   * @brief Sets the encoding files are read in.
+   *
+   * Labels are matched as in the WHATWG Encoding Standard, for example "utf-8", "latin1",
+   * "shift_jis", "euc-kr" or "utf-16le". Note that "latin1" and "iso-8859-1" select
+   * windows-1252. The encoding only affects SyntectFile, and only when the options are applied
+   * before the first line is read.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param encoding The encoding label, or NULL to detect UTF-8 and UTF-16 by byte order mark (the default).
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the encoding is known, false otherwise.
+   */
+  bool syntect_options_set_encoding(SyntectOptions *options, const char *encoding, const char **error);
+
+  /**
+   * @brief Sets how line endings are handled.
+   *
+   * Whatever the mode, a "\r" before the "\n" is kept out of the highlighted tokens, so it never
+   * ends up inside colored text. Switching to or from SYNTECT_LINE_ENDINGS_STRIP restarts the
+   * highlighter's parse state, so the options should be applied before the first line.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param mode The line ending mode. Defaults to SYNTECT_LINE_ENDINGS_PRESERVE.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if the mode is valid, false otherwise.
+   */
+  bool syntect_options_set_line_endings(SyntectOptions *options, SyntectLineEndings mode, const char **error);
+
+  /**
+   * @brief Sets the backgrounds of added and removed lines in highlighted diffs.
+   *
+   * Colors are given in CSS notation and may be translucent, in which case they are drawn over
+   * the theme background. By default the backgrounds come from the theme: the background of its
+   * markup.inserted and markup.deleted rules, or a faint tint of their foreground.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param added The background of added lines, or NULL to take it from the theme.
+   * @param removed The background of removed lines, or NULL to take it from the theme.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if both colors are valid, false otherwise.
+   */
+  bool syntect_options_set_diff_backgrounds(SyntectOptions *options, const char *added, const char *removed, const char **error);
+
+  /**
+   * @brief Sets the colors of search matches.
+   *
+   * Colors are given in CSS notation; a translucent background is drawn over the syntax
+   * background. When both are NULL, the default, matched text is drawn with its foreground and
+   * background swapped.
+   *
+   * @param options Pointer to the SyntectOptions.
+   * @param foreground The foreground of matched text, or NULL to keep the syntax color.
+   * @param background The background of matched text, or NULL to keep the syntax color.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true if both colors are valid, false otherwise.
+   */
+  bool syntect_options_set_match_colors(SyntectOptions *options, const char *foreground, const char *background, const char **error);
+
+  /**
+   * @brief Applies output options to a SyntectFile.
+   *
+   * The options are copied, and line numbering restarts at the first line number.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param options Pointer to the SyntectOptions.
+   */
+  void syntect_file_set_options(SyntectFile *wrapper, const SyntectOptions *options);
+
+  /**
+   * @brief Applies output options to a SyntectLines.
+   *
+   * The options are copied, and line numbering restarts at the first line number.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param options Pointer to the SyntectOptions.
+   */
+  void syntect_lines_set_options(SyntectLines *wrapper, const SyntectOptions *options);
+
+  /**
+   * @brief Gets the text the output format puts before the first line of a SyntectFile.
+   *
+   * For HTML this is a "<pre>" tag with the theme background; for ANSI it is empty.
+   * Fails for SYNTECT_OUTPUT_SVG, which cannot be written a line at a time.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_file_begin_document(SyntectFile *wrapper, const char **error);
+
+  /**
+   * @brief Gets the text the output format puts after the last line of a SyntectFile.
+   *
+   * @param wrapper Pointer to the SyntectFile.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_file_end_document(SyntectFile *wrapper, const char **error);
+
+  /**
+   * @brief Gets the text the output format puts before the first line of a SyntectLines.
+   *
+   * Fails for SYNTECT_OUTPUT_SVG, which cannot be written a line at a time.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_lines_begin_document(SyntectLines *wrapper, const char **error);
+
+  /**
+   * @brief Gets the text the output format puts after the last line of a SyntectLines.
+   *
+   * @param wrapper Pointer to the SyntectLines.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_lines_end_document(SyntectLines *wrapper, const char **error);
+
+  /**
+   * @brief Registers a theme under a name.
+   *
+   * syntect_create_file and syntect_create_lines look registered themes up before the default
+   * themes, so a registered name can also replace a default theme. A theme already registered
+   * under the same name is replaced. Highlighters that were created earlier keep the theme they
+   * were created with.
+   *
+   * @param theme_name The name to register the theme under.
+   * @param theme Pointer to the SyntectTheme. A copy is registered; the caller keeps ownership of it.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_register_theme(const char *theme_name, const SyntectTheme *theme, const char **error);
+
+  /**
+   * @brief Registers every theme of a ThemeSet under its name in the set.
+   *
+   * @param theme_set Pointer to the SyntectThemeSet. Copies are registered; the caller keeps ownership of it.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_register_theme_set(const SyntectThemeSet *theme_set, const char **error);
+
+  /**
+   * @brief Removes a theme from the registry.
+   *
+   * @param theme_name The name the theme was registered under.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if no theme is registered under that name.
+   */
+  bool syntect_unregister_theme(const char *theme_name, const char **error);
+
   /**
    * @brief Loads the default ThemeSet.
    *
@@ -134,7 +825,9 @@ extern "C"
   /**
    * @brief Loads a ThemeSet from a folder.
    *
-   * This function loads all themes found in the specified folder into a ThemeSet.
+   * This function loads all themes found in the specified folder and its subfolders into a ThemeSet.
+   * Both .tmTheme and .sublime-color-scheme files are loaded, named after their file name without extension.
+   * When a .tmTheme and a .sublime-color-scheme file have the same name, the .tmTheme file is loaded.
    *
    * @param folder The path to the folder containing the theme files.
    * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
@@ -142,6 +835,76 @@ extern "C"
    */
   SyntectThemeSet *syntect_load_theme_set_from_folder(const char *folder, const char **error);
 
+  /**
+   * @brief Loads a ThemeSet from a folder, going through the theme cache.
+   *
+   * Works like syntect_load_theme_set_from_folder, but when caching is enabled each theme is
+   * read from the theme cache if its source file has not changed since it was cached.
+   *
+   * @param folder The path to the folder containing the theme files.
+   * @param enable_caching Whether to use the theme cache (see syntect_set_theme_cache_dir).
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectThemeSet, or NULL if an error occurs.
+   */
+  SyntectThemeSet *syntect_load_theme_set_from_folder_with_caching(const char *folder, bool enable_caching, const char **error);
+
+  /**
+   * @brief Loads a ThemeSet from several folders.
+   *
+   * The folders are loaded in order, and a theme found in a later folder replaces a theme of
+   * the same name from an earlier one.
+   *
+   * @param folders An array of paths to folders containing theme files.
+   * @param count The number of folders in the array.
+   * @param enable_caching Whether to use the theme cache (see syntect_set_theme_cache_dir).
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectThemeSet, or NULL if an error occurs.
+   */
+  SyntectThemeSet *syntect_load_theme_set_from_folders(const char *const *folders, size_t count, bool enable_caching, const char **error);
+
+  /**
+   * @brief Creates an empty ThemeSet.
+   *
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectThemeSet. This should be freed using syntect_free_theme_set.
+   */
+  SyntectThemeSet *syntect_theme_set_new(const char **error);
+
+  /**
+   * @brief Adds a copy of a theme to a ThemeSet.
+   *
+   * A theme already in the set under the same name is replaced.
+   *
+   * @param theme_set Pointer to the SyntectThemeSet.
+   * @param theme_name The name to store the theme under.
+   * @param theme Pointer to the SyntectTheme to add. The caller keeps ownership of it.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_theme_set_add_theme(SyntectThemeSet *theme_set, const char *theme_name, const SyntectTheme *theme, const char **error);
+
+  /**
+   * @brief Removes a theme from a ThemeSet.
+   *
+   * @param theme_set Pointer to the SyntectThemeSet.
+   * @param theme_name The name of the theme to remove.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if the set has no theme of that name.
+   */
+  bool syntect_theme_set_remove_theme(SyntectThemeSet *theme_set, const char *theme_name, const char **error);
+
+  /**
+   * @brief Copies every theme of another ThemeSet into a ThemeSet.
+   *
+   * Themes of other replace themes of the same name in theme_set.
+   *
+   * @param theme_set Pointer to the SyntectThemeSet to add the themes to.
+   * @param other Pointer to the SyntectThemeSet to copy the themes from.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_theme_set_merge(SyntectThemeSet *theme_set, const SyntectThemeSet *other, const char **error);
+
   /**
    * @brief Gets the names of all themes in a ThemeSet.
    *
@@ -175,14 +938,79 @@ extern "C"
   /**
    * @brief Loads a Theme from a file.
    *
-   * This function loads a theme from the specified file.
+   * This function loads a theme from the specified file. Files with the .sublime-color-scheme
+   * extension are read as Sublime Text JSON color schemes, including their variables and color()
+   * mod functions. Any other file is read as a .tmTheme property list.
    *
    * @param theme_path The path to the theme file.
-   * @param enable_caching Whether to enable caching of the theme.
+   * @param enable_caching Whether to use the theme cache (see syntect_set_theme_cache_dir).
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectTheme, or NULL if an error occurs.
+   */
+  SyntectTheme *syntect_load_theme(const char *theme_path, bool enable_caching, const char **error);
+
+  /**
+   * @brief Loads a Theme from a VS Code color theme file.
+   *
+   * This function converts a VS Code JSON color theme. The "tokenColors" rules become theme rules,
+   * and editor colors such as "editor.background", "editor.lineHighlightBackground" and
+   * "editorLineNumber.foreground" become the matching global settings. Themes named in "include"
+   * are loaded first.
+   *
+   * @param theme_path The path to the VS Code theme file.
+   * @param enable_caching Whether to use the theme cache (see syntect_set_theme_cache_dir).
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectTheme, or NULL if an error occurs.
+   */
+  SyntectTheme *syntect_load_vscode_theme(const char *theme_path, bool enable_caching, const char **error);
+
+  /**
+   * @brief Builds a Theme from a Base16 or Base24 scheme file.
+   *
+   * This function reads the palette of a Base16 or Base24 scheme YAML file and applies it to the
+   * standard Base16 TextMate scope template. Both the classic layout (top-level "scheme" and
+   * "base00".."base0F" keys) and the "palette" layout are accepted. Schemes that define
+   * "base10".."base17" are treated as Base24.
+   *
+   * @param scheme_path The path to the scheme YAML file.
+   * @param enable_caching Whether to use the theme cache (see syntect_set_theme_cache_dir).
    * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
    * @return Pointer to the created SyntectTheme, or NULL if an error occurs.
    */
-  SyntectTheme *syntect_load_theme(const char *theme_path, int enable_caching, const char **error);
+  SyntectTheme *syntect_load_base16_theme(const char *scheme_path, bool enable_caching, const char **error);
+
+  /**
+   * @brief Sets the directory of the theme cache.
+   *
+   * Themes loaded with caching enabled are parsed once and stored in this directory as binary
+   * dumps. A cached theme is used again while its source file keeps the same modification time
+   * and size, or while its contents hash the same; otherwise it is parsed again. The directory
+   * is created when the first theme is cached. By default it is "$XDG_CACHE_HOME/syntect-c",
+   * falling back to "$HOME/.cache/syntect-c".
+   *
+   * @param dir The cache directory, or NULL to go back to the default.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_set_theme_cache_dir(const char *dir, const char **error);
+
+  /**
+   * @brief Gets the directory of the theme cache.
+   *
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The cache directory, or NULL if none can be determined. This should be freed using syntect_free_string.
+   */
+  const char *syntect_get_theme_cache_dir(const char **error);
+
+  /**
+   * @brief Removes every cached theme from the theme cache directory.
+   *
+   * Other files in the directory are left alone.
+   *
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_clear_theme_cache(const char **error);
 
   /**
    * @brief Gets a Theme from a ThemeSet.
@@ -224,6 +1052,206 @@ extern "C"
    */
   const char *syntect_get_theme_name(const SyntectTheme *theme);
 
+  /**
+   * @brief Creates an empty Theme.
+   *
+   * This function creates a theme with no global settings and no rules, to be filled in
+   * with syntect_theme_set_setting and syntect_theme_add_rule.
+   *
+   * @param name The name of the theme, or NULL to leave it unset.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the created SyntectTheme, or NULL if an error occurs. The theme should be freed using syntect_free_theme.
+   */
+  SyntectTheme *syntect_theme_new(const char *name, const char **error);
+
+  /**
+   * @brief Sets a global setting of a theme.
+   *
+   * The key is a tmTheme global setting such as "background", "foreground", "caret",
+   * "lineHighlight", "gutter" or "gutterForeground". The snake_case spelling ("line_highlight")
+   * is accepted as well, as are "name" and "author". Colors are given as "#RGB", "#RRGGBB"
+   * or "#RRGGBBAA".
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param key The name of the setting.
+   * @param value The new value of the setting, or NULL to clear it.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_theme_set_setting(SyntectTheme *theme, const char *key, const char *value, const char **error);
+
+  /**
+   * @brief Appends a rule to a theme.
+   *
+   * The rule applies the given style to the text matched by the scope selector,
+   * for example "comment" or "string - string.regexp, constant.numeric".
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param scope The scope selector of the rule.
+   * @param foreground The foreground color, or NULL to leave it unset.
+   * @param background The background color, or NULL to leave it unset.
+   * @param font_style A space separated list of "bold", "italic" and "underline", or NULL to leave it unset.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_theme_add_rule(SyntectTheme *theme, const char *scope, const char *foreground, const char *background, const char *font_style, const char **error);
+
+  /**
+   * @brief Gets the number of rules in a theme.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @return The number of rules in the theme.
+   */
+  size_t syntect_theme_get_rule_count(const SyntectTheme *theme);
+
+  /**
+   * @brief Removes a rule from a theme.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param index The index of the rule to remove, in the order the rules were added.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if the index is out of range.
+   */
+  bool syntect_theme_remove_rule(SyntectTheme *theme, size_t index, const char **error);
+
+  /**
+   * @brief Saves a theme to a file.
+   *
+   * The format is chosen from the extension of the path: ".tmTheme" writes a TextMate theme,
+   * ".tmdump" writes a syntect binary dump.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param path The path of the file to write.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return true on success, false if an error occurs.
+   */
+  bool syntect_save_theme(const SyntectTheme *theme, const char *path, const char **error);
+
+  /**
+   * @brief Exports a theme as a TextMate ".tmTheme" XML document.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The XML document, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_theme_to_tmtheme(const SyntectTheme *theme, const char **error);
+
+  /**
+   * @brief Exports a theme as a JSON document.
+   *
+   * The document has the layout of a ".sublime-color-scheme" file (name, author, globals and
+   * rules), so it can be written to such a file and loaded back with syntect_load_theme.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The JSON document, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_theme_to_json(const SyntectTheme *theme, const char **error);
+
+  /**
+   * @brief Exports a theme as a block of CSS custom properties.
+   *
+   * Global settings become "--syntect-<setting>" (for example "--syntect-line-highlight"),
+   * except the "popupCss" and "phantomCss" style sheets, which are left out.
+   * Each scope selector of a rule becomes "--syntect-<scope>-foreground", "-background",
+   * "-font-weight", "-font-style" and "-text-decoration". Dots and other characters that are not
+   * letters, digits or underscores become dashes, and the scopes of a descendant selector are
+   * joined by "--", so "meta.tag string" becomes "--syntect-meta-tag--string-foreground".
+   * Selectors with excludes, such as "source - comment", are left out. Selectors that differ
+   * only in the characters replaced by dashes, such as "meta-tag" and "meta.tag", get the same
+   * name, and the later rule wins.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param selector The CSS selector of the block, or NULL for ":root".
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The CSS text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_theme_to_css_variables(const SyntectTheme *theme, const char *selector, const char **error);
+
+  /**
+   * @brief Generates the LaTeX preamble lines needed by SYNTECT_OUTPUT_LATEX output.
+   *
+   * The preamble loads xcolor and defines each color of the theme as "syntect<RRGGBB>", such as
+   * "syntectB48EAD", along with "syntectforeground" and "syntectbackground" for its default
+   * colors. Translucent colors are defined as drawn over the theme background.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return The preamble text, or NULL if an error occurs. This should be freed using syntect_free_string.
+   */
+  const char *syntect_theme_to_latex_preamble(const SyntectTheme *theme, const char **error);
+
+  /**
+   * @brief Tells whether a theme is dark.
+   *
+   * A theme is dark when its background contrasts more with white than with black. Themes
+   * without a background are highlighted on white and are therefore light.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @return true if the theme is dark, false if it is light.
+   */
+  bool syntect_theme_is_dark(const SyntectTheme *theme);
+
+  /**
+   * @brief Gets the WCAG relative luminance of a theme's background.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @return The relative luminance, from 0 (black) to 1 (white).
+   */
+  double syntect_theme_get_background_luminance(const SyntectTheme *theme);
+
+  /**
+   * @brief Finds the rules of a theme whose foreground contrasts too little with the background.
+   *
+   * Each rule's foreground is measured against the rule's own background, or against the theme
+   * background when the rule has none. Rules without a foreground are skipped. WCAG asks for a
+   * ratio of at least 4.5 for normal text.
+   *
+   * @param theme Pointer to the SyntectTheme.
+   * @param min_ratio The lowest acceptable contrast ratio.
+   * @param count Pointer to store the number of rules found.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return An array of count issues, or NULL if there are none. This should be freed using syntect_free_contrast_issues.
+   */
+  SyntectContrastIssue *syntect_theme_find_low_contrast_rules(const SyntectTheme *theme, double min_ratio, size_t *count, const char **error);
+
+  /**
+   * @brief Frees an array returned by syntect_theme_find_low_contrast_rules.
+   *
+   * @param issues The array to be freed.
+   * @param count The number of issues in the array.
+   */
+  void syntect_free_contrast_issues(SyntectContrastIssue *issues, size_t count);
+
+  /**
+   * @brief Derives a new theme by layering override rules on top of a base theme.
+   *
+   * Global settings set in the overrides replace those of the base theme. Override rules take
+   * precedence over the base rules they match, including more specific ones: an override for
+   * "comment" also recolors a base rule for "comment.line", while keeping the attributes the
+   * override leaves unset. The override rules are usually built with syntect_theme_new and
+   * syntect_theme_add_rule.
+   *
+   * @param base Pointer to the base SyntectTheme. It is not modified.
+   * @param overrides Pointer to the SyntectTheme holding the override settings and rules.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the derived SyntectTheme, or NULL if an error occurs. The theme should be freed using syntect_free_theme.
+   */
+  SyntectTheme *syntect_theme_derive(const SyntectTheme *base, const SyntectTheme *overrides, const char **error);
+
+  /**
+   * @brief Derives a new theme by layering an override file on top of a base theme.
+   *
+   * The override file is a theme file that only contains the settings and rules to change.
+   * See syntect_theme_derive for how the overrides are applied.
+   *
+   * @param base Pointer to the base SyntectTheme. It is not modified.
+   * @param override_path The path to the override theme file.
+   * @param error Pointer to store the error message if the function fails. This should be freed using syntect_free_string.
+   * @return Pointer to the derived SyntectTheme, or NULL if an error occurs. The theme should be freed using syntect_free_theme.
+   */
+  SyntectTheme *syntect_theme_derive_from_file(const SyntectTheme *base, const char *override_path, const char **error);
+
   /**
    * @brief Frees a C string.
    *
@@ -233,6 +1261,14 @@ extern "C"
    */
   void syntect_free_string(char *s);
 
+  /**
+   * @brief Frees a buffer returned by one of the functions with an explicit output length.
+   *
+   * @param data Pointer to the buffer to be freed.
+   * @param len The length of the buffer, as stored in out_len.
+   */
+  void syntect_free_buffer(char *data, size_t len);
+
 #ifdef __cplusplus
 }
 #endif
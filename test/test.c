@@ -98,6 +98,43 @@ void test_highlight_text_line() {
     syntect_free_lines(wrapper);
 }
 
+/**
+ * @brief Tests building a theme from scratch and highlighting with it.
+ *
+ * This function creates an empty theme, sets its global settings, adds a rule,
+ * and uses the result to highlight a single line of text.
+ */
+void test_build_theme() {
+    const char *error = NULL;
+
+    SyntectTheme *theme = syntect_theme_new("Brand", &error);
+    check_error("syntect_theme_new", error);
+
+    syntect_theme_set_setting(theme, "background", "#101010", &error);
+    check_error("syntect_theme_set_setting", error);
+    syntect_theme_set_setting(theme, "foreground", "#F0F0F0", &error);
+    check_error("syntect_theme_set_setting", error);
+    syntect_theme_add_rule(theme, "keyword, storage", "#FF8800", NULL, "bold", &error);
+    check_error("syntect_theme_add_rule", error);
+
+    if (syntect_theme_get_rule_count(theme) != 1) {
+        fprintf(stderr, "Unexpected rule count\n");
+        exit(1);
+    }
+
+    SyntectLines *wrapper = syntect_create_lines_with_theme(theme, &error);
+    check_error("syntect_create_lines_with_theme", error);
+
+    const char *highlighted_line = syntect_highlight_text_line(wrapper, "fn main() {}", &error);
+    check_error("syntect_highlight_text_line", error);
+
+    printf("Highlighted line with built theme: %s\n", highlighted_line);
+    syntect_free_string((char*)highlighted_line);
+
+    syntect_free_lines(wrapper);
+    syntect_free_theme(theme);
+}
+
 /**
  * @brief Main function to run all tests.
  *
@@ -109,6 +146,7 @@ int main() {
     test_create_highlight_file();
     test_create_highlight_lines();
     test_highlight_text_line();
+    test_build_theme();
 
     printf("All tests passed!\n");
     return 0;